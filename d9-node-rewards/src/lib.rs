@@ -2,8 +2,15 @@
 
 use sp_staking::SessionIndex;
 mod structs;
+pub mod migration;
+pub mod runtime_api;
 use frame_support::{ traits::Currency, PalletId };
 pub use pallet::*;
+pub use structs::*;
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
 
 pub type BalanceOf<T> =
     <<T as pallet_contracts::Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
@@ -14,15 +21,35 @@ pub mod pallet {
     use frame_support::{
         inherent::Vec,
         pallet_prelude::{ DispatchResult,OptionQuery, * },
+        traits::{ ExistenceRequirement, Imbalance },
         weights::Weight,
     };
     use frame_system::pallet_prelude::*;
-    use pallet_d9_node_voting::NodeRewardManager;
+    use pallet_d9_node_voting::{
+        ContractEntry,
+        ContractOperation,
+        NodeRewardManager,
+        NodeVotingProvider,
+        ValidatorPerformance,
+    };
     use sp_runtime::traits::AccountIdConversion;
     use sp_runtime::traits::BadOrigin;
+    use sp_runtime::{
+        traits::{ SaturatedConversion, Zero },
+        FixedPointNumber,
+        FixedU128,
+        Perbill,
+        Percent,
+        Saturating,
+    };
+    use sp_std::collections::btree_map::BTreeMap;
     const STORAGE_VERSION: frame_support::traits::StorageVersion = frame_support::traits::StorageVersion::new(
-        1
+        2
     );
+    /// upper bound on sessions of earnings visited in one go, so a backlog is cleared gradually
+    const MAX_EARNINGS_SESSIONS_PRUNED_PER_CALL: u32 = 16;
+    /// upper bound on earnings entries removed in one go, the rest is removed at later sessions
+    const MAX_EARNINGS_PRUNED_PER_CALL: u32 = 512;
 
     #[pallet::pallet]
     #[pallet::storage_version(STORAGE_VERSION)]
@@ -32,7 +59,7 @@ pub mod pallet {
     pub trait Config: frame_system::Config + pallet_contracts::Config {
         type CurrencySubUnits: Get<BalanceOf<Self>>;
 
-        type Currency: Currency<Self::AccountId>;
+        type Currency: Currency<Self::AccountId, Balance = BalanceOf<Self>>;
 
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 
@@ -40,21 +67,301 @@ pub mod pallet {
 
         #[pallet::constant]
         type PalletId: Get<PalletId>;
-    }
 
-    #[pallet::storage]
-    #[pallet::getter(fn node_reward_contract)]
-    pub type NodeRewardContract<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
+        /// number of tier changes kept per node
+        #[pallet::constant]
+        type TierHistoryDepth: Get<u32>;
+
+        /// failed reward contract updates kept for retrying, the oldest is dropped when full
+        #[pallet::constant]
+        type MaxQueuedRewardUpdates: Get<u32>;
+
+        /// attempts, including the first, before a failed update is abandoned
+        #[pallet::constant]
+        type MaxRewardUpdateAttempts: Get<u32>;
+
+        /// blocks before the first retry, doubled after every further failure
+        #[pallet::constant]
+        type RewardUpdateRetryDelay: Get<Self::BlockNumber>;
+
+        /// most gas a single reward contract call may use, well below a block
+        #[pallet::constant]
+        type MaxRewardUpdateGas: Get<Weight>;
+
+        /// number of sessions whose per account earnings are kept
+        #[pallet::constant]
+        type EarningsHistoryDepth: Get<u32>;
+
+        /// origin that administers the pallet alongside `PalletAdmin`
+        type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// receives withheld rewards when `WithheldRewardsTo` is the treasury
+        type TreasuryAccount: Get<Self::AccountId>;
+
+        /// votes, delegations and candidates the rewards are computed from
+        type NodeVoting: NodeVotingProvider<Self::AccountId>;
+
+        /// most nodes paid in native mode at the end of a session, the best ranked go first
+        #[pallet::constant]
+        type MaxRewardedNodes: Get<u32>;
+    }
 
     #[pallet::storage]
     #[pallet::getter(fn pallet_admin)]
     pub type PalletAdmin<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn role_holder)]
+    pub type RoleHolders<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        AdminRole,
+        T::AccountId,
+        OptionQuery
+    >;
+
+    /// performance below which part of a node's native reward is withheld
+    ///
+    /// contract mode withholds nothing. the contract pays by its own rules and is sent the
+    /// votes unchanged, votes and rewards are not the same unit to cut.
+    #[pallet::storage]
+    #[pallet::getter(fn performance_thresholds)]
+    pub type NodePerformanceThresholds<T: Config> = StorageValue<
+        _,
+        PerformanceThresholds,
+        ValueQuery
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn withheld_rewards_destination)]
+    pub type WithheldRewardsTo<T: Config> = StorageValue<
+        _,
+        WithheldRewardsDestination,
+        ValueQuery
+    >;
+
+    /// no rewards are minted, distributed, claimed or sent to the contract while set
+    ///
+    /// sessions ending in contract mode are kept in `PausedRewardUpdates` and sent once the
+    /// rewards are resumed, sessions ending in native mode are not paid
+    #[pallet::storage]
+    #[pallet::getter(fn is_paused)]
+    pub type Paused<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn reward_distribution_mode)]
+    pub type DistributionMode<T: Config> = StorageValue<_, RewardDistributionMode, ValueQuery>;
+
+    /// amount paid out from the pot at the end of every session in native mode
+    ///
+    /// only used while no emission schedule is set
+    #[pallet::storage]
+    #[pallet::getter(fn reward_per_session)]
+    pub type RewardPerSession<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    /// tokens minted at every session end for native payouts, when set
+    #[pallet::storage]
+    #[pallet::getter(fn emission_schedule)]
+    pub type RewardEmissionSchedule<T: Config> = StorageValue<
+        _,
+        EmissionSchedule<BalanceOf<T>>,
+        OptionQuery
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn tier_cutoffs)]
+    pub type NodeTierCutoffs<T: Config> = StorageValue<_, TierCutoffs, ValueQuery>;
+
+    /// points of each tier the session reward is split by
+    #[pallet::storage]
+    #[pallet::getter(fn tier_weights)]
+    pub type NodeTierWeights<T: Config> = StorageValue<_, TierWeights, ValueQuery>;
+
+    /// tier of every ranked node as of the last session end
+    #[pallet::storage]
+    #[pallet::getter(fn node_tier)]
+    pub type CurrentNodeTiers<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        NodeTier,
+        OptionQuery
+    >;
+
+    /// most recent tier changes of a node, oldest first
+    #[pallet::storage]
+    #[pallet::getter(fn node_tier_history)]
+    pub type NodeTierHistory<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        BoundedVec<TierChange, T::TierHistoryDepth>,
+        ValueQuery
+    >;
+
+    /// reward a node's supporters have earned per vote of weight since the ledger started
+    #[pallet::storage]
+    #[pallet::getter(fn reward_per_vote)]
+    pub type RewardPerVote<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        FixedU128,
+        ValueQuery
+    >;
+
+    /// `RewardPerVote` of the node when the supporter's rewards from it were last settled
+    ///
+    /// node -> supporter -> reward per vote
+    #[pallet::storage]
+    pub type SupporterRewardSnapshots<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AccountId,
+        FixedU128,
+        ValueQuery
+    >;
+
+    /// settled rewards an account can claim
+    #[pallet::storage]
+    #[pallet::getter(fn unclaimed_rewards)]
+    pub type UnclaimedRewards<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        BalanceOf<T>,
+        ValueQuery
+    >;
+
+    /// supporter rewards accrued in the pot and not yet claimed, which new sessions cannot spend
+    #[pallet::storage]
+    #[pallet::getter(fn total_unclaimed_rewards)]
+    pub type TotalUnclaimedRewards<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    /// fractions of a unit supporters were owed but not credited when their rewards were settled
+    ///
+    /// whole units are released from `TotalUnclaimedRewards` back to the pot
+    #[pallet::storage]
+    pub type SettlementRoundingDust<T: Config> = StorageValue<_, FixedU128, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn contract_call_limits)]
+    pub type RewardContractCallLimits<T: Config> = StorageValue<
+        _,
+        ContractCallLimits<BalanceOf<T>>,
+        ValueQuery
+    >;
+
+    /// reward contract updates that failed, by the session they belong to
+    #[pallet::storage]
+    #[pallet::getter(fn failed_reward_update)]
+    pub type FailedRewardUpdates<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        SessionIndex,
+        FailedRewardUpdate<T::AccountId, T::BlockNumber>,
+        OptionQuery
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn number_of_failed_reward_updates)]
+    pub type NumberOfFailedRewardUpdates<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// node lists of sessions that ended while rewards were paused, by session
+    ///
+    /// kept apart from the failed updates so they are never dropped to make room, they are
+    /// sent to the contract once rewards are resumed
+    #[pallet::storage]
+    #[pallet::getter(fn paused_reward_update)]
+    pub type PausedRewardUpdates<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        SessionIndex,
+        BoundedVec<(T::AccountId, u64), ConstU32<300>>,
+        OptionQuery
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn number_of_paused_reward_updates)]
+    pub type NumberOfPausedRewardUpdates<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// rewards credited to an account in a session
+    ///
+    /// nodes are credited with their payout in the session it is for. supporters are credited
+    /// in the session their share was settled in, when they claimed or changed the delegation,
+    /// which can be later than the sessions it accrued in. only native payouts are recorded,
+    /// in contract mode the contract pays and keeps its own records.
+    #[pallet::storage]
+    #[pallet::getter(fn session_earnings)]
+    pub type SessionEarnings<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        SessionIndex,
+        Blake2_128Concat,
+        T::AccountId,
+        BalanceOf<T>,
+        ValueQuery
+    >;
+
+    /// oldest session that may still have earnings recorded
+    #[pallet::storage]
+    #[pallet::getter(fn oldest_earnings_session)]
+    pub type OldestEarningsSession<T: Config> = StorageValue<_, SessionIndex, ValueQuery>;
+
+    /// where pruning of `OldestEarningsSession` stopped, when it ran out of its limit
+    #[pallet::storage]
+    pub type EarningsPruneCursor<T: Config> = StorageValue<
+        _,
+        BoundedVec<u8, ConstU32<128>>,
+        OptionQuery
+    >;
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         ErrorIssuingRewards,
         ContractError(DispatchError),
+        RewardDistributionModeUpdated(RewardDistributionMode),
+        RewardPerSessionUpdated(BalanceOf<T>),
+        /// [session, node, node_reward, supporters_reward_accrued]
+        NodeRewarded(SessionIndex, T::AccountId, BalanceOf<T>, BalanceOf<T>),
+        /// paid to nodes, reserved for supporters and sent to the treasury [session, total_paid]
+        RewardsDistributed(SessionIndex, BalanceOf<T>),
+        /// the pot could not cover the full session reward [session, available]
+        RewardPotInsufficient(SessionIndex, BalanceOf<T>),
+        TierCutoffsUpdated(TierCutoffs),
+        TierWeightsUpdated(TierWeights),
+        /// `None` means the node is not ranked [node, session, from, to]
+        NodeTierChanged(T::AccountId, SessionIndex, Option<NodeTier>, Option<NodeTier>),
+        /// [supporter, amount]
+        RewardsClaimed(T::AccountId, BalanceOf<T>),
+        RewardUpdateQueued(SessionIndex),
+        /// [session, attempt, succeeded]
+        RewardUpdateAttempted(SessionIndex, u32, bool),
+        RewardUpdateRecovered(SessionIndex),
+        /// the update failed `MaxRewardUpdateAttempts` times and was removed from the queue
+        RewardUpdateAbandoned(SessionIndex),
+        /// the queue was full and the oldest update was removed to make room
+        RewardUpdateDropped(SessionIndex),
+        ContractCallLimitsUpdated(ContractCallLimits<BalanceOf<T>>),
+        EmissionScheduleUpdated(Option<EmissionSchedule<BalanceOf<T>>>),
+        /// the part of the emission that was paid out [session, amount_minted, total_issuance]
+        RewardsMinted(SessionIndex, BalanceOf<T>, BalanceOf<T>),
+        /// the schedule's emission was cut short by the issuance cap [session, cut_emission]
+        EmissionCapReached(SessionIndex, BalanceOf<T>),
+        PalletAdminUpdated(T::AccountId),
+        /// [role, holder]
+        RoleUpdated(AdminRole, Option<T::AccountId>),
+        RewardsPaused,
+        RewardsResumed,
+        /// the session ended while rewards were paused
+        RewardDistributionSkipped(SessionIndex),
+        PerformanceThresholdsUpdated(PerformanceThresholds),
+        WithheldRewardsDestinationUpdated(WithheldRewardsDestination),
+        /// [session, node, reason, amount_withheld]
+        RewardWithheld(SessionIndex, T::AccountId, WithholdReason, BalanceOf<T>),
     }
 
     #[pallet::error]
@@ -62,6 +369,42 @@ pub mod pallet {
         RestrictedAccess,
         NodeRewardContractNotSet,
         ErrorUpdatingNodeRewardContract,
+        TierCutoffsNotAscending,
+        TierWeightsAllZero,
+        NoRewardsToClaim,
+        RewardPotInsufficient,
+        NoFailedRewardUpdate,
+        ContractGasLimitTooHigh,
+        DistributionPaused,
+        NotPaused,
+        TooManyRewardedNodes,
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+        fn on_idle(n: T::BlockNumber, remaining_weight: Weight) -> Weight {
+            if Paused::<T>::get() {
+                return T::DbWeight::get().reads(1);
+            }
+            let consumed = Self::send_paused_reward_updates(remaining_weight);
+            consumed.saturating_add(
+                Self::retry_failed_reward_updates(n, remaining_weight.saturating_sub(consumed))
+            )
+        }
+
+        fn integrity_test() {
+            assert!(
+                T::MaxRewardUpdateGas::get().all_lt(T::BlockWeights::get().max_block),
+                "MaxRewardUpdateGas has to leave room in the block"
+            );
+            // native payouts run in the session hook, so paying every rewarded node must fit
+            assert!(
+                Self::native_distribution_weight(T::MaxRewardedNodes::get() as u64).all_lte(
+                    T::BlockWeights::get().max_block
+                ),
+                "paying MaxRewardedNodes nodes does not fit in a block"
+            );
+        }
     }
 
     #[pallet::call]
@@ -69,92 +412,990 @@ pub mod pallet {
         #[pallet::call_index(0)]
         #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
         pub fn set_pallet_admin(origin: OriginFor<T>, new_admin: T::AccountId) -> DispatchResult {
-            Self::root_or_admin(origin)?;
-            PalletAdmin::<T>::put(new_admin);
+            Self::ensure_admin(origin)?;
+            PalletAdmin::<T>::put(new_admin.clone());
+            Self::deposit_event(Event::PalletAdminUpdated(new_admin));
             Ok(())
         }
 
+        /// register `new_contract` as the node reward contract with the original selector
+        ///
+        /// kept for existing callers, `set_reward_contract` also takes the selector and ABI
+        /// version. the contract is dry run as it is called at session end, by the pallet
+        /// account with the current session and no nodes.
         #[pallet::call_index(1)]
-        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        #[pallet::weight(
+            T::DbWeight::get().reads(3).saturating_add(T::NodeVoting::set_contract_entry_weight())
+        )]
         pub fn set_node_reward_contract(
             origin: OriginFor<T>,
             new_contract: T::AccountId
         ) -> DispatchResult {
-            Self::root_or_admin(origin)?;
-            NodeRewardContract::<T>::put(new_contract);
+            Self::ensure_role(origin, AdminRole::ContractManager)?;
+            let entry = ContractEntry {
+                address: new_contract,
+                selector: ContractOperation::UpdateRewards.legacy_selector(),
+                abi_version: 1
+            };
+            let no_nodes: Vec<(T::AccountId, u64)> = Vec::new();
+            T::NodeVoting::set_contract_entry(
+                ContractOperation::UpdateRewards,
+                Some(entry),
+                Some(Self::account_id()),
+                (T::NodeVoting::current_session(), no_nodes).encode()
+            )
+        }
+
+        #[pallet::call_index(2)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_reward_distribution_mode(
+            origin: OriginFor<T>,
+            mode: RewardDistributionMode
+        ) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            DistributionMode::<T>::put(mode);
+            Self::deposit_event(Event::RewardDistributionModeUpdated(mode));
+            Ok(())
+        }
+
+        #[pallet::call_index(3)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_reward_per_session(
+            origin: OriginFor<T>,
+            reward: BalanceOf<T>
+        ) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            RewardPerSession::<T>::put(reward);
+            Self::deposit_event(Event::RewardPerSessionUpdated(reward));
+            Ok(())
+        }
+
+        /// set the last rank of each tier, takes effect when the current session ends
+        ///
+        /// every rewarded rank has to be within `MaxRewardedNodes`
+        #[pallet::call_index(4)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_tier_cutoffs(origin: OriginFor<T>, cutoffs: TierCutoffs) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            if !cutoffs.is_ascending() {
+                return Err(Error::<T>::TierCutoffsNotAscending.into());
+            }
+            if cutoffs.standby > T::MaxRewardedNodes::get() {
+                return Err(Error::<T>::TooManyRewardedNodes.into());
+            }
+            NodeTierCutoffs::<T>::put(cutoffs.clone());
+            Self::deposit_event(Event::TierCutoffsUpdated(cutoffs));
+            Ok(())
+        }
+
+        /// pay out the rewards the caller earned as a supporter since its last claim
+        #[pallet::call_index(5)]
+        #[pallet::weight(
+            T::DbWeight::get()
+                .reads_writes(3, 3)
+                .saturating_add(
+                    Pallet::<T>
+                        ::supporter_settlement_weight()
+                        .saturating_add(T::DbWeight::get().reads(1))
+                        .saturating_mul(T::NodeVoting::max_candidates() as u64)
+                )
+        )]
+        pub fn claim_rewards(origin: OriginFor<T>) -> DispatchResult {
+            let supporter = ensure_signed(origin)?;
+            if Paused::<T>::get() {
+                return Err(Error::<T>::DistributionPaused.into());
+            }
+            Self::settle_all_delegations(&supporter);
+            let amount = UnclaimedRewards::<T>::take(supporter.clone());
+            if amount.is_zero() {
+                return Err(Error::<T>::NoRewardsToClaim.into());
+            }
+            <T as Config>::Currency
+                ::transfer(&Self::account_id(), &supporter, amount, ExistenceRequirement::KeepAlive)
+                .map_err(|_| Error::<T>::RewardPotInsufficient)?;
+            TotalUnclaimedRewards::<T>::mutate(|total| {
+                *total = total.saturating_sub(amount);
+            });
+            Self::deposit_event(Event::RewardsClaimed(supporter, amount));
+            Ok(())
+        }
+
+        /// replay a failed reward contract update now, regardless of its backoff
+        ///
+        /// charged for `MaxRewardUpdateGas`, the gas the call did not use is refunded
+        #[pallet::call_index(6)]
+        #[pallet::weight(
+            T::DbWeight::get().reads_writes(4, 2).saturating_add(T::MaxRewardUpdateGas::get())
+        )]
+        pub fn retry_reward_update(
+            origin: OriginFor<T>,
+            session: SessionIndex
+        ) -> DispatchResultWithPostInfo {
+            Self::ensure_role(origin, AdminRole::ContractManager)?;
+            if Paused::<T>::get() {
+                return Err(Error::<T>::DistributionPaused.into());
+            }
+            if !FailedRewardUpdates::<T>::contains_key(session) {
+                return Err(Error::<T>::NoFailedRewardUpdate.into());
+            }
+            let consumed = Self::attempt_failed_reward_update(
+                session,
+                frame_system::Pallet::<T>::block_number()
+            );
+            Ok(Some(T::DbWeight::get().reads(2).saturating_add(consumed)).into())
+        }
+
+        /// set the gas and storage deposit limits of reward contract calls
+        ///
+        /// neither the computation time nor the proof size of the gas limit may exceed
+        /// `MaxRewardUpdateGas`
+        #[pallet::call_index(7)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_contract_call_limits(
+            origin: OriginFor<T>,
+            limits: ContractCallLimits<BalanceOf<T>>
+        ) -> DispatchResult {
+            Self::ensure_role(origin, AdminRole::ContractManager)?;
+            if limits.gas_limit.any_gt(T::MaxRewardUpdateGas::get()) {
+                return Err(Error::<T>::ContractGasLimitTooHigh.into());
+            }
+            RewardContractCallLimits::<T>::put(limits.clone());
+            Self::deposit_event(Event::ContractCallLimitsUpdated(limits));
+            Ok(())
+        }
+
+        /// set or clear the schedule of tokens minted as rewards every session
+        ///
+        /// while a schedule is set it replaces the fixed session reward in native mode, only the
+        /// part that is actually paid out is minted. contract mode mints nothing.
+        #[pallet::call_index(8)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_emission_schedule(
+            origin: OriginFor<T>,
+            schedule: Option<EmissionSchedule<BalanceOf<T>>>
+        ) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            match schedule.clone() {
+                Some(schedule) => RewardEmissionSchedule::<T>::put(schedule),
+                None => RewardEmissionSchedule::<T>::kill(),
+            }
+            Self::deposit_event(Event::EmissionScheduleUpdated(schedule));
+            Ok(())
+        }
+
+        /// give a role to an account, or take it away
+        #[pallet::call_index(9)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_role(
+            origin: OriginFor<T>,
+            role: AdminRole,
+            holder: Option<T::AccountId>
+        ) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            match holder.clone() {
+                Some(account) => RoleHolders::<T>::insert(role, account),
+                None => RoleHolders::<T>::remove(role),
+            }
+            Self::deposit_event(Event::RoleUpdated(role, holder));
+            Ok(())
+        }
+
+        /// stop minting, distributing and claiming rewards until an admin resumes them
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 1))]
+        pub fn pause_rewards(origin: OriginFor<T>) -> DispatchResult {
+            Self::ensure_role(origin, AdminRole::PauseGuardian)?;
+            if Paused::<T>::get() {
+                return Err(Error::<T>::DistributionPaused.into());
+            }
+            Paused::<T>::put(true);
+            Self::deposit_event(Event::RewardsPaused);
+            Ok(())
+        }
+
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 1))]
+        pub fn resume_rewards(origin: OriginFor<T>) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            if !Paused::<T>::get() {
+                return Err(Error::<T>::NotPaused.into());
+            }
+            Paused::<T>::put(false);
+            Self::deposit_event(Event::RewardsResumed);
+            Ok(())
+        }
+
+        /// set when nodes lose part of their native reward, takes effect when the session ends
+        #[pallet::call_index(12)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_performance_thresholds(
+            origin: OriginFor<T>,
+            thresholds: PerformanceThresholds
+        ) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            NodePerformanceThresholds::<T>::put(thresholds.clone());
+            Self::deposit_event(Event::PerformanceThresholdsUpdated(thresholds));
             Ok(())
         }
 
-      
-        
+        #[pallet::call_index(13)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_withheld_rewards_destination(
+            origin: OriginFor<T>,
+            destination: WithheldRewardsDestination
+        ) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            WithheldRewardsTo::<T>::put(destination);
+            Self::deposit_event(Event::WithheldRewardsDestinationUpdated(destination));
+            Ok(())
+        }
+
+        /// register or remove the node reward contract in the voting pallet's registry
+        ///
+        /// a new entry is dry run with `call_args` first, as the caller when it is signed and
+        /// as the contract itself otherwise
+        #[pallet::call_index(14)]
+        #[pallet::weight(
+            T::DbWeight::get().reads(2).saturating_add(T::NodeVoting::set_contract_entry_weight())
+        )]
+        pub fn set_reward_contract(
+            origin: OriginFor<T>,
+            entry: Option<ContractEntry<T::AccountId>>,
+            call_args: Vec<u8>
+        ) -> DispatchResult {
+            let caller = ensure_signed(origin.clone()).ok();
+            Self::ensure_role(origin, AdminRole::ContractManager)?;
+            T::NodeVoting::set_contract_entry(
+                ContractOperation::UpdateRewards,
+                entry,
+                caller,
+                call_args
+            )
+        }
+
+        /// set the points of each tier the session reward is split by, takes effect when the
+        /// current session ends
+        #[pallet::call_index(15)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_tier_weights(origin: OriginFor<T>, weights: TierWeights) -> DispatchResult {
+            Self::ensure_admin(origin)?;
+            if weights.is_zero() {
+                return Err(Error::<T>::TierWeightsAllZero.into());
+            }
+            NodeTierWeights::<T>::put(weights.clone());
+            Self::deposit_event(Event::TierWeightsUpdated(weights));
+            Ok(())
+        }
     }
 
     impl<T: Config> Pallet<T> {
-        fn root_or_admin(origin: OriginFor<T>) -> Result<(), BadOrigin> {
-            let origin = ensure_signed_or_root(origin)?;
-            match origin {
-                Some(caller) => {
-                    let admin = PalletAdmin::<T>::get();
-                    if admin.is_some() && admin.unwrap() == caller {
-                        return Ok(());
-                    } else {
-                        return Err(BadOrigin);
-                    }
-                }
-                None => {
+        /// passes for `AdminOrigin` and the pallet admin
+        fn ensure_admin(origin: OriginFor<T>) -> Result<(), BadOrigin> {
+            Self::ensure_admin_or(origin, None)
+        }
+
+        /// passes for the admins and the holder of `role`
+        fn ensure_role(origin: OriginFor<T>, role: AdminRole) -> Result<(), BadOrigin> {
+            Self::ensure_admin_or(origin, Some(role))
+        }
+
+        fn ensure_admin_or(origin: OriginFor<T>, role: Option<AdminRole>) -> Result<(), BadOrigin> {
+            let origin = match T::AdminOrigin::try_origin(origin) {
+                Ok(_) => {
                     return Ok(());
                 }
+                Err(origin) => origin,
+            };
+            let caller = ensure_signed(origin)?;
+            if PalletAdmin::<T>::get() == Some(caller.clone()) {
+                return Ok(());
             }
+            if role.is_some() && RoleHolders::<T>::get(role.unwrap()) == Some(caller) {
+                return Ok(());
+            }
+            Err(BadOrigin)
         }
 
         fn account_id() -> T::AccountId {
             T::PalletId::get().into_account_truncating()
         }
 
+        /// assign every ranked node its tier for the session that ended, recording changes
+        ///
+        /// nodes that dropped out of the ranking lose their tier
+        fn assign_tiers(
+            end_index: SessionIndex,
+            sorted_nodes: &Vec<(T::AccountId, u64)>
+        ) -> Vec<(T::AccountId, NodeTier)> {
+            let cutoffs = NodeTierCutoffs::<T>::get();
+            let node_tiers: Vec<(T::AccountId, NodeTier)> = sorted_nodes
+                .iter()
+                .enumerate()
+                .map(|(index, (node, _))| {
+                    (node.clone(), cutoffs.tier_for_rank((index as u32).saturating_add(1)))
+                })
+                .collect();
+
+            let dropped_nodes: Vec<(T::AccountId, NodeTier)> = CurrentNodeTiers::<T>
+                ::iter()
+                .filter(|(node, _)| !node_tiers.iter().any(|(ranked, _)| ranked == node))
+                .collect();
+            for (node, previous_tier) in dropped_nodes {
+                CurrentNodeTiers::<T>::remove(node.clone());
+                Self::deposit_event(
+                    Event::NodeTierChanged(node, end_index, Some(previous_tier), None)
+                );
+            }
+
+            for (node, tier) in node_tiers.iter() {
+                let previous_tier = CurrentNodeTiers::<T>::get(node.clone());
+                if previous_tier.as_ref() == Some(tier) {
+                    continue;
+                }
+                CurrentNodeTiers::<T>::insert(node.clone(), tier.clone());
+                NodeTierHistory::<T>::mutate(node.clone(), |history| {
+                    if history.is_full() {
+                        history.remove(0);
+                    }
+                    let _ = history.try_push((end_index, tier.clone()));
+                });
+                Self::deposit_event(
+                    Event::NodeTierChanged(
+                        node.clone(),
+                        end_index,
+                        previous_tier,
+                        Some(tier.clone())
+                    )
+                );
+            }
+            node_tiers
+        }
+
+        /// split the session reward over the nodes by tier, then each node's reward with its
+        /// supporters by its sharing percent and their share of its votes
+        ///
+        /// returns the weight used and the amount paid or reserved for supporters
+        fn distribute_native_rewards(
+            end_index: SessionIndex,
+            node_tiers: Vec<(T::AccountId, NodeTier)>,
+            validator_performance: Vec<(T::AccountId, ValidatorPerformance)>,
+            mut session_reward: BalanceOf<T>,
+            source: PayoutSource
+        ) -> (Weight, BalanceOf<T>) {
+            // an emission is minted as it is paid, so only payouts from the pot are capped
+            if source == PayoutSource::Pot {
+                let available = <T as Config>::Currency
+                    ::free_balance(&Self::account_id())
+                    .saturating_sub(<T as Config>::Currency::minimum_balance())
+                    .saturating_sub(TotalUnclaimedRewards::<T>::get());
+                if session_reward > available {
+                    Self::deposit_event(Event::RewardPotInsufficient(end_index, available));
+                    session_reward = available;
+                }
+            }
+            if session_reward.is_zero() {
+                return (T::DbWeight::get().reads(4), Zero::zero());
+            }
+
+            // node tiers follow the ranking, so the cap drops the lowest ranked nodes
+            let tier_weights = NodeTierWeights::<T>::get();
+            let node_weights: Vec<(T::AccountId, u32)> = node_tiers
+                .into_iter()
+                .map(|(node, tier)| (node, tier_weights.weight_of(&tier)))
+                .filter(|(_, weight)| *weight > 0)
+                .take(T::MaxRewardedNodes::get() as usize)
+                .collect();
+            let total_weight: u64 = node_weights
+                .iter()
+                .fold(0u64, |total, (_, weight)| total.saturating_add(*weight as u64));
+            if total_weight == 0 {
+                return (T::DbWeight::get().reads(4), Zero::zero());
+            }
+            let number_of_nodes = node_weights.len() as u64;
+            let performance: BTreeMap<T::AccountId, ValidatorPerformance> = validator_performance
+                .into_iter()
+                .collect();
+            let thresholds = NodePerformanceThresholds::<T>::get();
+            let withheld_to = WithheldRewardsTo::<T>::get();
+
+            let mut total_paid: BalanceOf<T> = Zero::zero();
+            for (node, weight) in node_weights {
+                let mut node_reward = Perbill::from_rational(
+                    weight as u64,
+                    total_weight
+                ).mul_floor(session_reward);
+                let withholdings = Self::withheld_rewards(
+                    end_index,
+                    &node,
+                    node_reward,
+                    performance.get(&node),
+                    &thresholds
+                );
+                let mut total_withheld: BalanceOf<T> = Zero::zero();
+                for (reason, amount) in withholdings {
+                    total_withheld = total_withheld.saturating_add(amount);
+                    Self::deposit_event(
+                        Event::RewardWithheld(end_index, node.clone(), reason, amount)
+                    );
+                }
+                node_reward = node_reward.saturating_sub(total_withheld);
+                // a failed transfer leaves the withheld rewards in the pot
+                if
+                    withheld_to == WithheldRewardsDestination::Treasury &&
+                    Self::pay_reward(source, &T::TreasuryAccount::get(), total_withheld)
+                {
+                    total_paid = total_paid.saturating_add(total_withheld);
+                }
+
+                let sharing_percent = T::NodeVoting::supporter_share(&node);
+                let supporters_pool = Percent::from_percent(sharing_percent).mul_floor(node_reward);
+
+                // supporters collect their part through `claim_rewards`. whatever cannot be
+                // credited to them stays with the node
+                let delegated_votes = T::NodeVoting::delegated_votes(&node);
+                let supporters_accrued = Self::accrue_supporter_rewards(
+                    &node,
+                    delegated_votes,
+                    supporters_pool,
+                    source
+                );
+
+                let node_share = node_reward.saturating_sub(supporters_accrued);
+                let mut node_paid: BalanceOf<T> = Zero::zero();
+                if Self::pay_reward(source, &node, node_share) {
+                    node_paid = node_share;
+                    Self::record_earnings(end_index, &node, node_paid);
+                }
+                total_paid = total_paid
+                    .saturating_add(node_paid)
+                    .saturating_add(supporters_accrued);
+                Self::deposit_event(
+                    Event::NodeRewarded(end_index, node, node_paid, supporters_accrued)
+                );
+            }
+            Self::deposit_event(Event::RewardsDistributed(end_index, total_paid));
+            (Self::native_distribution_weight(number_of_nodes), total_paid)
+        }
+
+        /// weight of paying `number_of_nodes` nodes in native mode
+        ///
+        /// per node: metadata, votes, accumulator, jail record, earnings and the balances of
+        /// the transfers, including the treasury
+        fn native_distribution_weight(number_of_nodes: u64) -> Weight {
+            T::DbWeight::get().reads_writes(
+                number_of_nodes.saturating_mul(9).saturating_add(6),
+                number_of_nodes.saturating_mul(7).saturating_add(1)
+            )
+        }
+
+        /// weight of settling one delegation of a supporter
+        ///
+        /// the accumulator, snapshot, rounding dust, both ledgers, the session and the earnings
+        pub fn supporter_settlement_weight() -> Weight {
+            T::DbWeight::get().reads_writes(7, 5)
+        }
+
+        /// raise the node's reward per vote by `pool` spread over its `delegated_votes`
+        ///
+        /// returns the amount reserved for the supporters, which the rounding of the reward
+        /// per vote can leave below `pool`. zero when nobody supports the node, or when an
+        /// emission could not be minted into the pot to hold it.
+        fn accrue_supporter_rewards(
+            node: &T::AccountId,
+            delegated_votes: u64,
+            pool: BalanceOf<T>,
+            source: PayoutSource
+        ) -> BalanceOf<T> {
+            if delegated_votes == 0 || pool.is_zero() {
+                return Zero::zero();
+            }
+            let increase = FixedU128::saturating_from_rational(
+                pool.saturated_into::<u128>(),
+                delegated_votes as u128
+            );
+            let reserved: BalanceOf<T> = increase
+                .saturating_mul_int(delegated_votes as u128)
+                .saturated_into::<BalanceOf<T>>()
+                .min(pool);
+            // the pot holds supporter rewards until they are claimed
+            if
+                source == PayoutSource::Emission &&
+                !Self::pay_reward(source, &Self::account_id(), reserved)
+            {
+                return Zero::zero();
+            }
+            RewardPerVote::<T>::mutate(node.clone(), |reward_per_vote| {
+                *reward_per_vote = reward_per_vote.saturating_add(increase);
+            });
+            TotalUnclaimedRewards::<T>::mutate(|total| {
+                *total = total.saturating_add(reserved);
+            });
+            reserved
+        }
+
+        /// rewards `supporter` earned from `node` since the last settlement at `weight`
+        fn unsettled_rewards(
+            supporter: &T::AccountId,
+            node: &T::AccountId,
+            weight: u64
+        ) -> BalanceOf<T> {
+            Self::owed_rewards(supporter, node, weight).saturating_mul_int(1u128).saturated_into()
+        }
+
+        /// exact amount `supporter` is owed by `node` at `weight`, including fractions of a unit
+        fn owed_rewards(supporter: &T::AccountId, node: &T::AccountId, weight: u64) -> FixedU128 {
+            let reward_per_vote = RewardPerVote::<T>::get(node.clone());
+            let snapshot = SupporterRewardSnapshots::<T>::get(node.clone(), supporter.clone());
+            reward_per_vote
+                .saturating_sub(snapshot)
+                .saturating_mul(FixedU128::saturating_from_integer(weight))
+        }
+
+        /// move what `supporter` earned from `node` at `weight` into its unclaimed rewards
+        ///
+        /// only whole units are credited. the fractions left over are collected and released
+        /// back to the pot once they add up to a unit.
+        fn settle_supporter(supporter: &T::AccountId, node: &T::AccountId, weight: u64) {
+            let owed = Self::owed_rewards(supporter, node, weight);
+            let earned: BalanceOf<T> = owed.saturating_mul_int(1u128).saturated_into();
+            if !owed.frac().is_zero() {
+                let dust = SettlementRoundingDust::<T>::get().saturating_add(owed.frac());
+                let released: BalanceOf<T> = dust.saturating_mul_int(1u128).saturated_into();
+                TotalUnclaimedRewards::<T>::mutate(|total| {
+                    *total = total.saturating_sub(released);
+                });
+                SettlementRoundingDust::<T>::put(dust.frac());
+            }
+            if !earned.is_zero() {
+                UnclaimedRewards::<T>::mutate(supporter.clone(), |unclaimed| {
+                    *unclaimed = unclaimed.saturating_add(earned);
+                });
+                let current_session = T::NodeVoting::current_session();
+                Self::record_earnings(current_session, supporter, earned);
+            }
+            SupporterRewardSnapshots::<T>::insert(
+                node.clone(),
+                supporter.clone(),
+                RewardPerVote::<T>::get(node.clone())
+            );
+        }
+
+        fn settle_all_delegations(supporter: &T::AccountId) {
+            for node in T::NodeVoting::delegations_of(supporter) {
+                let weight = T::NodeVoting::delegation_weight(supporter, &node);
+                Self::settle_supporter(supporter, &node, weight);
+            }
+        }
+
+        /// rewards `supporter` could claim right now
+        pub fn pending_rewards(supporter: &T::AccountId) -> BalanceOf<T> {
+            T::NodeVoting
+                ::delegations_of(supporter)
+                .into_iter()
+                .fold(UnclaimedRewards::<T>::get(supporter.clone()), |total, node| {
+                    let weight = T::NodeVoting::delegation_weight(supporter, &node);
+                    total.saturating_add(Self::unsettled_rewards(supporter, &node, weight))
+                })
+        }
+
+        /// parts of `node_reward` withheld for underperforming in `session`, each taken from
+        /// what remains
+        fn withheld_rewards(
+            session: SessionIndex,
+            node: &T::AccountId,
+            node_reward: BalanceOf<T>,
+            performance: Option<&ValidatorPerformance>,
+            thresholds: &PerformanceThresholds
+        ) -> Vec<(WithholdReason, BalanceOf<T>)> {
+            let mut withholdings: Vec<(WithholdReason, BalanceOf<T>)> = Vec::new();
+            let mut remaining = node_reward;
+            // only validators of the session have performance to judge
+            if let Some(performance) = performance {
+                if performance.blocks_authored < thresholds.min_blocks_authored {
+                    let kept = Perbill::from_rational(
+                        performance.blocks_authored,
+                        thresholds.min_blocks_authored
+                    ).mul_floor(remaining);
+                    withholdings.push((
+                        WithholdReason::TooFewBlocksAuthored,
+                        remaining.saturating_sub(kept),
+                    ));
+                    remaining = kept;
+                }
+                if !performance.heartbeat_received {
+                    let withheld = thresholds.offline_penalty.mul_floor(remaining);
+                    withholdings.push((WithholdReason::Offline, withheld));
+                    remaining = remaining.saturating_sub(withheld);
+                }
+            }
+            if T::NodeVoting::was_jailed_in(node, session) {
+                let withheld = thresholds.jailed_penalty.mul_floor(remaining);
+                withholdings.push((WithholdReason::Jailed, withheld));
+            }
+            withholdings.retain(|(_, amount)| !amount.is_zero());
+            withholdings
+        }
+
+        fn record_earnings(session: SessionIndex, account: &T::AccountId, amount: BalanceOf<T>) {
+            SessionEarnings::<T>::mutate(session, account.clone(), |earned| {
+                *earned = earned.saturating_add(amount);
+            });
+        }
+
+        /// drop the earnings of sessions older than `EarningsHistoryDepth`
+        ///
+        /// removes at most `MAX_EARNINGS_PRUNED_PER_CALL` entries, a session that is not
+        /// cleared by then is continued from the stored cursor next time
+        fn prune_earnings(end_index: SessionIndex) -> Weight {
+            let first_retained = end_index
+                .saturating_add(1)
+                .saturating_sub(T::EarningsHistoryDepth::get());
+            let mut oldest = OldestEarningsSession::<T>::get();
+            let mut cursor = EarningsPruneCursor::<T>::take();
+            let mut pruned_sessions: u32 = 0;
+            let mut removed: u32 = 0;
+            let mut visited: u32 = 0;
+            while
+                oldest < first_retained &&
+                pruned_sessions < MAX_EARNINGS_SESSIONS_PRUNED_PER_CALL &&
+                removed < MAX_EARNINGS_PRUNED_PER_CALL
+            {
+                let removal = SessionEarnings::<T>::clear_prefix(
+                    oldest,
+                    MAX_EARNINGS_PRUNED_PER_CALL.saturating_sub(removed),
+                    cursor.as_ref().map(|cursor| cursor.as_slice())
+                );
+                removed = removed.saturating_add(removal.unique);
+                visited = visited.saturating_add(removal.loops);
+                pruned_sessions = pruned_sessions.saturating_add(1);
+                match removal.maybe_cursor {
+                    // a cursor that does not fit restarts the session, whose removed entries
+                    // are gone by then
+                    Some(next_cursor) => {
+                        cursor = BoundedVec::try_from(next_cursor).ok();
+                        break;
+                    }
+                    None => {
+                        cursor = None;
+                        oldest = oldest.saturating_add(1);
+                    }
+                }
+            }
+            OldestEarningsSession::<T>::put(oldest);
+            if let Some(cursor) = cursor {
+                EarningsPruneCursor::<T>::put(cursor);
+            }
+            // the session and the cursor
+            T::DbWeight::get().reads_writes(
+                (visited as u64).saturating_add(2),
+                (removed as u64).saturating_add(2)
+            )
+        }
+
+        /// rewards credited to `account` in the retained sessions from `from` to `to` inclusive
+        ///
+        /// supporter rewards are listed under the session they were settled in
+        pub fn earnings_between(
+            account: &T::AccountId,
+            from: SessionIndex,
+            to: SessionIndex
+        ) -> Vec<(SessionIndex, BalanceOf<T>)> {
+            let from = from.max(OldestEarningsSession::<T>::get());
+            let to = to.min(from.saturating_add(T::EarningsHistoryDepth::get()));
+            (from..=to)
+                .filter_map(|session| {
+                    let earned = SessionEarnings::<T>::get(session, account.clone());
+                    if earned.is_zero() { None } else { Some((session, earned)) }
+                })
+                .collect()
+        }
+
+        /// free balance of the pot, including rewards accrued to supporters but not claimed
+        pub fn pot_balance() -> BalanceOf<T> {
+            <T as Config>::Currency::free_balance(&Self::account_id())
+        }
+
+        /// pay `amount` to `recipient` from `source`, returning whether it was paid
+        ///
+        /// an emission is minted straight to the recipient, so nothing that cannot be paid
+        /// enters the issuance
+        fn pay_reward(
+            source: PayoutSource,
+            recipient: &T::AccountId,
+            amount: BalanceOf<T>
+        ) -> bool {
+            if amount.is_zero() {
+                return false;
+            }
+            match source {
+                PayoutSource::Pot =>
+                    <T as Config>::Currency
+                        ::transfer(
+                            &Self::account_id(),
+                            recipient,
+                            amount,
+                            ExistenceRequirement::KeepAlive
+                        )
+                        .is_ok(),
+                PayoutSource::Emission =>
+                    !<T as Config>::Currency::deposit_creating(recipient, amount).peek().is_zero(),
+            }
+        }
+
+        /// tokens the emission schedule releases in `session`, before the issuance cap
+        pub fn scheduled_emission(session: SessionIndex) -> BalanceOf<T> {
+            let Some(schedule) = RewardEmissionSchedule::<T>::get() else {
+                return Zero::zero();
+            };
+            if session < schedule.start_session {
+                return Zero::zero();
+            }
+            if schedule.decay_interval == 0 {
+                return schedule.base_per_session;
+            }
+            let decays = (session - schedule.start_session) / schedule.decay_interval;
+            schedule.retained_per_interval
+                .saturating_pow(decays as usize)
+                .mul_floor(schedule.base_per_session)
+        }
+
+        /// the session's emission up to the issuance cap, `None` when no schedule is set
+        fn capped_session_emission(end_index: SessionIndex) -> Option<BalanceOf<T>> {
+            let Some(schedule) = RewardEmissionSchedule::<T>::get() else {
+                return None;
+            };
+            let scheduled = Self::scheduled_emission(end_index);
+            let headroom = schedule.max_total_issuance.saturating_sub(
+                <T as Config>::Currency::total_issuance()
+            );
+            let amount = scheduled.min(headroom);
+            if amount < scheduled {
+                let cut_emission = scheduled.saturating_sub(amount);
+                Self::deposit_event(Event::EmissionCapReached(end_index, cut_emission));
+            }
+            Some(amount)
+        }
+
+        /// pay the session's emission out natively, minting only what is distributed
+        ///
+        /// payouts are minted to the nodes and the treasury, supporter rewards into the pot
+        /// that holds them until they are claimed. the pot's own funds are left alone.
+        fn distribute_session_emission(
+            end_index: SessionIndex,
+            emission: BalanceOf<T>,
+            node_tiers: Vec<(T::AccountId, NodeTier)>,
+            validator_performance: Vec<(T::AccountId, ValidatorPerformance)>
+        ) -> Weight {
+            let (weight, total_paid) = Self::distribute_native_rewards(
+                end_index,
+                node_tiers,
+                validator_performance,
+                emission,
+                PayoutSource::Emission
+            );
+            if !total_paid.is_zero() {
+                Self::deposit_event(
+                    Event::RewardsMinted(
+                        end_index,
+                        total_paid,
+                        <T as Config>::Currency::total_issuance()
+                    )
+                );
+            }
+            // the issuance
+            weight.saturating_add(T::DbWeight::get().reads(1))
+        }
+
+        /// returns the weight the call consumed along with its outcome
         fn update_rewards_on_contract(
             end_index: SessionIndex,
             sorted_nodes: Vec<(T::AccountId, u64)>
-        ) -> Result<(), Error<T>> {
-            // let sorted_nodes_vec = sorted_nodes
-            //     .iter()
-            //     .cloned() // Create owned copies
-            //     .collect::<Vec<T::AccountId>>();
-
-            //0x93440f8d
-            //0x93440f8d
-            //update_rewards
-            let mut selector: Vec<u8> = [0x93, 0x44, 0x0f, 0x8d].into();
-            let mut encoded_index = (end_index as u32).encode();
-            let mut encoded_nodes: Vec<u8> = sorted_nodes.encode();
-            let mut data_for_contract_call = Vec::new();
-            data_for_contract_call.append(&mut selector);
-            data_for_contract_call.append(&mut encoded_index);
-            data_for_contract_call.append(&mut encoded_nodes);
-
-            let node_reward_contract_opt = NodeRewardContract::<T>::get();
+        ) -> (Weight, Result<(), Error<T>>) {
+            let lookup_weight = T::DbWeight::get().reads(2);
+            let node_reward_contract_opt = T::NodeVoting::contract_entry(
+                ContractOperation::UpdateRewards
+            );
             if node_reward_contract_opt.is_none() {
-                return Err(Error::<T>::NodeRewardContractNotSet);
+                return (lookup_weight, Err(Error::<T>::NodeRewardContractNotSet));
             }
 
             let node_reward_contract = node_reward_contract_opt.unwrap();
-            let weight: Weight = Weight::from_parts(2_000_000_000_000, u64::MAX);
+            let data_for_contract_call = node_reward_contract.call_data(
+                (end_index, sorted_nodes).encode()
+            );
+            let limits = RewardContractCallLimits::<T>::get();
             let send_value: BalanceOf<T> = (0u32).into();
-            let contract_call_result = pallet_contracts::Pallet::<T>::bare_call(
+            let contract_call = pallet_contracts::Pallet::<T>::bare_call(
                 Self::account_id(),
-                node_reward_contract,
+                node_reward_contract.address,
                 send_value,
-                weight,
-                None,
+                Self::reward_update_gas_limit(),
+                limits.storage_deposit_limit,
                 data_for_contract_call,
                 false,
                 pallet_contracts::Determinism::Enforced
-            ).result;
-            match contract_call_result {
+            );
+            let consumed = lookup_weight.saturating_add(contract_call.gas_consumed);
+            let outcome = match contract_call.result {
+                Ok(return_value) if return_value.did_revert() =>
+                    Err(Error::<T>::ErrorUpdatingNodeRewardContract),
                 Ok(_) => Ok(()),
                 Err(err) => {
                     Self::deposit_event(Event::ContractError(err));
-                  Err(Error::<T>::ErrorUpdatingNodeRewardContract)}
+                    Err(Error::<T>::ErrorUpdatingNodeRewardContract)
+                }
+            };
+            (consumed, outcome)
+        }
+
+        /// gas a reward contract call is given, the configured limit capped by
+        /// `MaxRewardUpdateGas`
+        fn reward_update_gas_limit() -> Weight {
+            RewardContractCallLimits::<T>::get().gas_limit.min(T::MaxRewardUpdateGas::get())
+        }
+
+        /// keep a failed update for retrying, making room by dropping the oldest one
+        fn queue_failed_reward_update(
+            end_index: SessionIndex,
+            sorted_nodes: Vec<(T::AccountId, u64)>,
+            attempts: u32
+        ) {
+            if NumberOfFailedRewardUpdates::<T>::get() >= T::MaxQueuedRewardUpdates::get() {
+                let oldest_session_opt = FailedRewardUpdates::<T>::iter_keys().min();
+                if oldest_session_opt.is_none() {
+                    return;
+                }
+                let oldest_session = oldest_session_opt.unwrap();
+                FailedRewardUpdates::<T>::remove(oldest_session);
+                NumberOfFailedRewardUpdates::<T>::mutate(|count| {
+                    *count = count.saturating_sub(1);
+                });
+                Self::deposit_event(Event::RewardUpdateDropped(oldest_session));
+            }
+            let next_attempt_at = frame_system::Pallet::<T>
+                ::block_number()
+                .saturating_add(T::RewardUpdateRetryDelay::get());
+            FailedRewardUpdates::<T>::insert(end_index, FailedRewardUpdate {
+                sorted_nodes: BoundedVec::truncate_from(sorted_nodes),
+                attempts,
+                next_attempt_at,
+            });
+            NumberOfFailedRewardUpdates::<T>::mutate(|count| {
+                *count = count.saturating_add(1);
+            });
+            Self::deposit_event(Event::RewardUpdateQueued(end_index));
+        }
+
+        /// send the sessions that ended while rewards were paused, oldest first, as long as a
+        /// full contract call still fits
+        ///
+        /// a session the contract rejects joins the failed updates to be retried from there
+        fn send_paused_reward_updates(remaining_weight: Weight) -> Weight {
+            let mut consumed = T::DbWeight::get().reads(1);
+            let number_of_updates = NumberOfPausedRewardUpdates::<T>::get();
+            if number_of_updates == 0 {
+                return consumed;
+            }
+            let mut sessions: Vec<SessionIndex> = PausedRewardUpdates::<T>
+                ::iter_keys()
+                .collect();
+            sessions.sort();
+            consumed = consumed.saturating_add(T::DbWeight::get().reads(number_of_updates as u64));
+            let attempt_weight = T::DbWeight::get()
+                .reads_writes(4, 4)
+                .saturating_add(Self::reward_update_gas_limit());
+            for session in sessions {
+                if remaining_weight.any_lt(consumed.saturating_add(attempt_weight)) {
+                    break;
+                }
+                let sorted_nodes = PausedRewardUpdates::<T>
+                    ::take(session)
+                    .unwrap_or_default()
+                    .into_inner();
+                NumberOfPausedRewardUpdates::<T>::mutate(|count| {
+                    *count = count.saturating_sub(1);
+                });
+                let (call_weight, outcome) = Self::update_rewards_on_contract(
+                    session,
+                    sorted_nodes.clone()
+                );
+                consumed = consumed
+                    .saturating_add(T::DbWeight::get().reads_writes(2, 2))
+                    .saturating_add(call_weight);
+                Self::deposit_event(Event::RewardUpdateAttempted(session, 1, outcome.is_ok()));
+                if outcome.is_err() {
+                    Self::queue_failed_reward_update(session, sorted_nodes, 1);
+                    consumed = consumed.saturating_add(T::DbWeight::get().reads_writes(2, 2));
+                }
+            }
+            consumed
+        }
+
+        /// retry queued updates that are due, as long as a full contract call still fits
+        fn retry_failed_reward_updates(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+            let mut consumed = T::DbWeight::get().reads(1);
+            if NumberOfFailedRewardUpdates::<T>::get() == 0 {
+                return consumed;
+            }
+            let due_sessions: Vec<SessionIndex> = FailedRewardUpdates::<T>
+                ::iter()
+                .filter(|(_, update)| update.next_attempt_at <= now)
+                .map(|(session, _)| session)
+                .collect();
+            consumed = consumed.saturating_add(
+                T::DbWeight::get().reads(NumberOfFailedRewardUpdates::<T>::get() as u64)
+            );
+            // an attempt may use the full gas limit, so only start one when that still fits
+            let attempt_weight = T::DbWeight::get()
+                .reads_writes(4, 2)
+                .saturating_add(Self::reward_update_gas_limit());
+            for session in due_sessions {
+                if remaining_weight.any_lt(consumed.saturating_add(attempt_weight)) {
+                    break;
+                }
+                let attempt_consumed = Self::attempt_failed_reward_update(session, now);
+                consumed = consumed.saturating_add(attempt_consumed);
+            }
+            consumed
+        }
+
+        /// call the contract again with a queued update, then clear it or schedule the next try
+        ///
+        /// returns the weight used
+        fn attempt_failed_reward_update(session: SessionIndex, now: T::BlockNumber) -> Weight {
+            let update_opt = FailedRewardUpdates::<T>::get(session);
+            if update_opt.is_none() {
+                return T::DbWeight::get().reads(1);
+            }
+            let mut update = update_opt.unwrap();
+            update.attempts = update.attempts.saturating_add(1);
+            let (call_weight, outcome) = Self::update_rewards_on_contract(
+                session,
+                update.sorted_nodes.clone().into_inner()
+            );
+            let consumed = T::DbWeight::get().reads_writes(2, 2).saturating_add(call_weight);
+            let succeeded = outcome.is_ok();
+            Self::deposit_event(Event::RewardUpdateAttempted(session, update.attempts, succeeded));
+
+            if succeeded || update.attempts >= T::MaxRewardUpdateAttempts::get() {
+                FailedRewardUpdates::<T>::remove(session);
+                NumberOfFailedRewardUpdates::<T>::mutate(|count| {
+                    *count = count.saturating_sub(1);
+                });
+                if succeeded {
+                    Self::deposit_event(Event::RewardUpdateRecovered(session));
+                } else {
+                    Self::deposit_event(Event::RewardUpdateAbandoned(session));
+                }
+                return consumed;
             }
+            // wait twice as long after every failed retry
+            let backoff = T::RewardUpdateRetryDelay::get().saturating_mul(
+                (2u32).saturating_pow(update.attempts.saturating_sub(1)).into()
+            );
+            update.next_attempt_at = now.saturating_add(backoff);
+            FailedRewardUpdates::<T>::insert(session, update);
+            consumed
         }
     }
 
@@ -162,16 +1403,102 @@ pub mod pallet {
         /// pull data to update the pool
         fn update_rewards(
             end_index: SessionIndex,
-            sorted_node_list: Vec<(T::AccountId, u64)>
-        ) -> () {
-            let contract_update_result = Self::update_rewards_on_contract(
+            sorted_node_list: Vec<(T::AccountId, u64)>,
+            validator_performance: Vec<(T::AccountId, ValidatorPerformance)>
+        ) -> Weight {
+            let number_of_nodes = sorted_node_list.len() as u64;
+            let node_tiers = Self::assign_tiers(end_index, &sorted_node_list);
+            if Paused::<T>::get() {
+                // tiers still follow the rankings, nothing is minted or paid for the session
+                Self::deposit_event(Event::RewardDistributionSkipped(end_index));
+                let tiers_weight = T::DbWeight::get().reads_writes(
+                    number_of_nodes.saturating_mul(2).saturating_add(4),
+                    number_of_nodes.saturating_mul(2)
+                );
+                if DistributionMode::<T>::get() == RewardDistributionMode::Contract {
+                    // the contract is sent the session once rewards are resumed
+                    PausedRewardUpdates::<T>::insert(
+                        end_index,
+                        BoundedVec::truncate_from(sorted_node_list)
+                    );
+                    NumberOfPausedRewardUpdates::<T>::mutate(|count| {
+                        *count = count.saturating_add(1);
+                    });
+                    return tiers_weight.saturating_add(T::DbWeight::get().reads_writes(2, 2));
+                }
+                return tiers_weight;
+            }
+            let pruning_weight = Self::prune_earnings(end_index);
+            // the current tier of every node and of nodes that dropped out, plus history
+            let tiers_weight = T::DbWeight::get()
+                .reads_writes(
+                    number_of_nodes.saturating_mul(2).saturating_add(3),
+                    number_of_nodes.saturating_mul(2)
+                )
+                .saturating_add(pruning_weight);
+            // the emission schedule only mints for native payouts, the contract pays out of
+            // its own funds
+            if DistributionMode::<T>::get() == RewardDistributionMode::Native {
+                let native_weight = match Self::capped_session_emission(end_index) {
+                    Some(emission) =>
+                        Self::distribute_session_emission(
+                            end_index,
+                            emission,
+                            node_tiers,
+                            validator_performance
+                        ),
+                    None => {
+                        let (weight, _) = Self::distribute_native_rewards(
+                            end_index,
+                            node_tiers,
+                            validator_performance,
+                            RewardPerSession::<T>::get(),
+                            PayoutSource::Pot
+                        );
+                        weight
+                    }
+                };
+                // the mode and the schedule
+                return tiers_weight
+                    .saturating_add(T::DbWeight::get().reads(2))
+                    .saturating_add(native_weight);
+            }
+            // the contract gets the votes as they are, performance is only judged for native
+            // payouts as the contract pays by its own rules
+            let (call_weight, contract_update_result) = Self::update_rewards_on_contract(
                 end_index,
-                sorted_node_list
+                sorted_node_list.clone()
             );
+            let consumed = tiers_weight.saturating_add(call_weight);
             if contract_update_result.is_err() {
                 Self::deposit_event(Event::ErrorIssuingRewards);
-                return;
+                Self::queue_failed_reward_update(end_index, sorted_node_list, 1);
+                return consumed.saturating_add(T::DbWeight::get().reads_writes(2, 2));
             }
+            consumed
+        }
+
+        fn on_delegation_weight_change(
+            voter: &T::AccountId,
+            candidate: &T::AccountId,
+            previous_weight: u64
+        ) {
+            Self::settle_supporter(voter, candidate, previous_weight);
+        }
+
+        fn on_delegation_removed(voter: &T::AccountId, candidate: &T::AccountId) {
+            SupporterRewardSnapshots::<T>::remove(candidate.clone(), voter.clone());
+        }
+
+        fn on_candidate_removed(candidate: &T::AccountId) {
+            // the snapshots of its supporters went with their delegations, any left over
+            // would be read against a reward per vote that starts again from zero
+            RewardPerVote::<T>::remove(candidate.clone());
+            let _ = SupporterRewardSnapshots::<T>::clear_prefix(candidate.clone(), u32::MAX, None);
+        }
+
+        fn delegation_weight_change_weight() -> Weight {
+            Self::supporter_settlement_weight()
         }
     }
 }
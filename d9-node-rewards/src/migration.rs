@@ -0,0 +1,86 @@
+use super::*;
+use frame_support::{
+    pallet_prelude::*,
+    storage_alias,
+    traits::{ GetStorageVersion, OnRuntimeUpgrade },
+    weights::Weight,
+};
+use pallet_d9_node_voting::{
+    ContractEntry,
+    ContractOperation,
+    ContractRegistry,
+    NodeVotingProvider,
+};
+#[cfg(feature = "try-runtime")]
+use sp_std::vec::Vec;
+
+/// moves the node reward contract into the contract registry of the voting pallet and starts
+/// the earnings history at the session of the upgrade
+pub mod v2 {
+    use super::*;
+
+    #[storage_alias]
+    type NodeRewardContract<T: Config> = StorageValue<
+        Pallet<T>,
+        <T as frame_system::Config>::AccountId,
+        OptionQuery
+    >;
+
+    /// writes to the registry of the voting pallet directly, so it needs both configs
+    pub struct MigrateToV2<T>(PhantomData<T>);
+    impl<T: Config + pallet_d9_node_voting::Config> OnRuntimeUpgrade for MigrateToV2<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let onchain_version = Pallet::<T>::on_chain_storage_version();
+            if onchain_version != 1 {
+                return T::DbWeight::get().reads(1);
+            }
+
+            // an entry registered through the voting pallet already takes precedence
+            let node_reward_contract_opt = NodeRewardContract::<T>::take();
+            let operation = ContractOperation::UpdateRewards;
+            let already_registered = ContractRegistry::<T>::contains_key(operation);
+            if node_reward_contract_opt.is_some() && !already_registered {
+                ContractRegistry::<T>::insert(operation, ContractEntry {
+                    address: node_reward_contract_opt.unwrap(),
+                    selector: operation.legacy_selector(),
+                    abi_version: 1,
+                });
+            }
+            // no earnings were recorded before, so pruning has nothing older to walk
+            OldestEarningsSession::<T>::put(T::NodeVoting::current_session());
+            StorageVersion::new(2).put::<Pallet<T>>();
+            T::DbWeight::get().reads_writes(4, 4)
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+            let migrated = Pallet::<T>::on_chain_storage_version() == 1;
+            Ok((migrated, NodeRewardContract::<T>::get()).encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+            let (migrated, node_reward_contract_opt): (bool, Option<T::AccountId>) = Decode::decode(
+                &mut &state[..]
+            ).map_err(|_| "invalid pre-upgrade state")?;
+            if !migrated {
+                return Ok(());
+            }
+            ensure!(!NodeRewardContract::<T>::exists(), "the old reward contract was not removed");
+            ensure!(
+                node_reward_contract_opt.is_none() ||
+                    ContractRegistry::<T>::contains_key(ContractOperation::UpdateRewards),
+                "the reward contract was not registered"
+            );
+            ensure!(
+                OldestEarningsSession::<T>::get() == T::NodeVoting::current_session(),
+                "the earnings history does not start at the current session"
+            );
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() == 2,
+                "storage version was not set to 2"
+            );
+            Ok(())
+        }
+    }
+}
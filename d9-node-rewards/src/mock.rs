@@ -0,0 +1,206 @@
+use crate as pallet_d9_node_rewards;
+use frame_support::{
+    parameter_types,
+    traits::{
+        ConstBool, ConstU32, ConstU64, ConstU8, Everything, GenesisBuild, Nothing, Randomness, Time,
+    },
+    weights::Weight,
+    PalletId,
+};
+use frame_system::EnsureRoot;
+use pallet_d9_node_voting::ValidatorHeartbeats;
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{AccountIdConversion, BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+pub const ALICE: u64 = 1;
+pub const BOB: u64 = 2;
+pub const CHARLIE: u64 = 3;
+pub const DAVE: u64 = 4;
+pub const TREASURY: u64 = 99;
+/// held by the pot at genesis, on top of which the tests pay rewards
+pub const POT_FUNDS: u64 = 10_000;
+
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        Contracts: pallet_contracts,
+        NodeVoting: pallet_d9_node_voting,
+        NodeRewards: pallet_d9_node_rewards,
+    }
+);
+
+parameter_types! {
+    pub BlockWeights: frame_system::limits::BlockWeights =
+        frame_system::limits::BlockWeights::simple_max(
+            Weight::from_parts(2_000_000_000_000, u64::MAX),
+        );
+    pub ContractsSchedule: pallet_contracts::Schedule<Test> = Default::default();
+    pub const RewardsPalletId: PalletId = PalletId(*b"d9/nrwrd");
+    pub const MaxRewardUpdateGas: Weight = Weight::from_parts(500_000_000_000, 5 * 1024 * 1024);
+    pub const TreasuryAccount: u64 = TREASURY;
+    pub MaxBurnContractGas: Weight = Weight::from_parts(10_000_000_000, 1_000_000);
+}
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = Everything;
+    type BlockWeights = BlockWeights;
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+    type Balance = u64;
+    type RuntimeEvent = RuntimeEvent;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU64<1>;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type HoldIdentifier = ();
+    type FreezeIdentifier = ();
+    type MaxHolds = ();
+    type MaxFreezes = ();
+}
+
+/// no contract is ever deployed in these tests, so time and randomness stay constant
+pub struct MockTime;
+impl Time for MockTime {
+    type Moment = u64;
+    fn now() -> u64 {
+        0
+    }
+}
+
+pub struct MockRandomness;
+impl Randomness<H256, u64> for MockRandomness {
+    fn random(_subject: &[u8]) -> (H256, u64) {
+        (H256::zero(), 0)
+    }
+}
+
+impl pallet_contracts::Config for Test {
+    type Time = MockTime;
+    type Randomness = MockRandomness;
+    type Currency = Balances;
+    type RuntimeEvent = RuntimeEvent;
+    type RuntimeCall = RuntimeCall;
+    type CallFilter = Nothing;
+    type WeightPrice = ();
+    type WeightInfo = ();
+    type ChainExtension = ();
+    type Schedule = ContractsSchedule;
+    type CallStack = [pallet_contracts::Frame<Self>; 5];
+    type DepositPerByte = ConstU64<1>;
+    type DefaultDepositLimit = ConstU64<1_000_000>;
+    type DepositPerItem = ConstU64<1>;
+    type AddressGenerator = pallet_contracts::DefaultAddressGenerator;
+    type MaxCodeLen = ConstU32<{ 123 * 1024 }>;
+    type MaxStorageKeyLen = ConstU32<128>;
+    type UnsafeUnstableInterface = ConstBool<false>;
+    type MaxDebugBufferLen = ConstU32<{ 2 * 1024 * 1024 }>;
+}
+
+/// validator performance is handed to the reward manager directly in these tests
+pub struct AllOnline;
+impl ValidatorHeartbeats<u64> for AllOnline {
+    fn is_online(_validator: &u64) -> bool {
+        true
+    }
+}
+
+impl pallet_d9_node_voting::Config for Test {
+    type CurrencySubUnits = ConstU64<1>;
+    type Currency = Balances;
+    type RuntimeEvent = RuntimeEvent;
+    type MaxCandidates = ConstU32<10>;
+    type MaxValidatorNodes = ConstU32<5>;
+    type NodeRewardManager = NodeRewards;
+    type MaxDelegationPolicyEntries = ConstU32<4>;
+    type ValidatorHeartbeats = AllOnline;
+    type HistoryDepth = ConstU32<4>;
+    type SharePercentNoticePeriod = ConstU32<2>;
+    type MaxSharePercentDrop = ConstU8<10>;
+    type MaxBurnContractGas = MaxBurnContractGas;
+    type ConvictionPeriod = ConstU32<2>;
+    type MetadataDepositBase = ConstU64<10>;
+    type MetadataDepositPerByte = ConstU64<0>;
+    type SessionKeysRegistered = Everything;
+    type RetirementNoticePeriod = ConstU32<2>;
+    type JailDuration = ConstU32<3>;
+}
+
+impl pallet_d9_node_rewards::Config for Test {
+    type CurrencySubUnits = ConstU64<1>;
+    type Currency = Balances;
+    type RuntimeEvent = RuntimeEvent;
+    type PalletId = RewardsPalletId;
+    type TierHistoryDepth = ConstU32<4>;
+    type MaxQueuedRewardUpdates = ConstU32<2>;
+    type MaxRewardUpdateAttempts = ConstU32<3>;
+    type RewardUpdateRetryDelay = ConstU64<2>;
+    type MaxRewardUpdateGas = MaxRewardUpdateGas;
+    type EarningsHistoryDepth = ConstU32<2>;
+    type AdminOrigin = EnsureRoot<u64>;
+    type TreasuryAccount = TreasuryAccount;
+    type NodeVoting = NodeVoting;
+    type MaxRewardedNodes = ConstU32<10>;
+}
+
+/// account of the reward pot
+pub fn pot() -> u64 {
+    RewardsPalletId::get().into_account_truncating()
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut storage = frame_system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![
+            (ALICE, 100_000),
+            (BOB, 100_000),
+            (CHARLIE, 100_000),
+            (DAVE, 100_000),
+            (pot(), POT_FUNDS),
+        ],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
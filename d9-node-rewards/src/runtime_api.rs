@@ -0,0 +1,20 @@
+use codec::Codec;
+use sp_staking::SessionIndex;
+use sp_std::prelude::*;
+
+sp_api::decl_runtime_apis! {
+    pub trait NodeRewardsApi<AccountId, Balance> where AccountId: Codec, Balance: Codec {
+        /// rewards credited to an account per session from `from` to `to`, sessions without
+        /// earnings are left out
+        ///
+        /// supporter rewards are listed under the session they were settled in, and only
+        /// native payouts are recorded
+        fn get_earnings(account: AccountId, from: SessionIndex, to: SessionIndex) -> Vec<(SessionIndex, Balance)>;
+
+        /// supporter rewards that can be claimed right now
+        fn get_pending_rewards(account: AccountId) -> Balance;
+
+        /// free balance of the reward pot
+        fn get_pot_balance() -> Balance;
+    }
+}
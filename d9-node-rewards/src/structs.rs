@@ -1,4 +1,6 @@
-use frame_support::pallet_prelude::*;
+use frame_support::{ pallet_prelude::*, weights::Weight };
+use sp_runtime::Perbill;
+use sp_staking::SessionIndex;
 // use substrate_fixed::{ FixedU128, types::extra::U30 };
 use codec::MaxEncodedLen;
 // pub type FixedBalance = FixedU128<U30>;
@@ -37,3 +39,285 @@ pub enum SuperNodeSubTier {
     Middle,
     Lower,
 }
+
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub enum RewardDistributionMode {
+    /// forward the node list to the node reward contract
+    #[default]
+    Contract,
+    /// pay nodes and their supporters from the pallet's pot
+    Native,
+}
+
+/// last rank (1-based, by votes) of each tier, ranks past `standby` are candidates
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct TierCutoffs {
+    pub upper_super: u32,
+    pub middle_super: u32,
+    pub lower_super: u32,
+    pub standby: u32,
+}
+
+impl Default for TierCutoffs {
+    fn default() -> Self {
+        TierCutoffs {
+            upper_super: 9,
+            middle_super: 18,
+            lower_super: 27,
+            standby: 288,
+        }
+    }
+}
+
+impl TierCutoffs {
+    pub fn is_ascending(&self) -> bool {
+        self.upper_super <= self.middle_super &&
+            self.middle_super <= self.lower_super &&
+            self.lower_super <= self.standby
+    }
+
+    pub fn tier_for_rank(&self, rank: u32) -> NodeTier {
+        match rank {
+            r if r <= self.upper_super => NodeTier::Super(SuperNodeSubTier::Upper),
+            r if r <= self.middle_super => NodeTier::Super(SuperNodeSubTier::Middle),
+            r if r <= self.lower_super => NodeTier::Super(SuperNodeSubTier::Lower),
+            r if r <= self.standby => NodeTier::StandBy,
+            _ => NodeTier::Candidate,
+        }
+    }
+}
+
+/// points a node of each tier receives when the session reward is split
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct TierWeights {
+    pub upper_super: u32,
+    pub middle_super: u32,
+    pub lower_super: u32,
+    pub standby: u32,
+    pub candidate: u32,
+}
+
+impl Default for TierWeights {
+    /// super nodes produce the blocks and share most of the reward, one step per sub tier.
+    /// standby nodes get a small part for staying ready to step in, candidates nothing.
+    fn default() -> Self {
+        TierWeights {
+            upper_super: 6,
+            middle_super: 5,
+            lower_super: 4,
+            standby: 1,
+            candidate: 0,
+        }
+    }
+}
+
+impl TierWeights {
+    pub fn weight_of(&self, tier: &NodeTier) -> u32 {
+        match tier {
+            NodeTier::Super(SuperNodeSubTier::Upper) => self.upper_super,
+            NodeTier::Super(SuperNodeSubTier::Middle) => self.middle_super,
+            NodeTier::Super(SuperNodeSubTier::Lower) => self.lower_super,
+            NodeTier::StandBy => self.standby,
+            NodeTier::Candidate => self.candidate,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.upper_super == 0 &&
+            self.middle_super == 0 &&
+            self.lower_super == 0 &&
+            self.standby == 0 &&
+            self.candidate == 0
+    }
+}
+
+/// tier a node moved into and the session it happened in
+pub type TierChange = (SessionIndex, NodeTier);
+
+/// node list of a session whose reward contract call failed, kept for another attempt
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct FailedRewardUpdate<AccountId, BlockNumber> {
+    pub sorted_nodes: BoundedVec<(AccountId, u64), ConstU32<300>>,
+    pub attempts: u32,
+    pub next_attempt_at: BlockNumber,
+}
+
+/// limits the node reward contract is called with
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct ContractCallLimits<Balance> {
+    pub gas_limit: Weight,
+    /// `None` lets the call use as much storage deposit as it needs
+    pub storage_deposit_limit: Option<Balance>,
+}
+
+impl<Balance> Default for ContractCallLimits<Balance> {
+    fn default() -> Self {
+        ContractCallLimits {
+            // a quarter of a two second block with a bounded proof, the runtime's
+            // `MaxRewardUpdateGas` still applies on top
+            gas_limit: Weight::from_parts(500_000_000_000, 5 * 1024 * 1024),
+            storage_deposit_limit: None,
+        }
+    }
+}
+
+/// how much is minted for native payouts at the end of every session
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct EmissionSchedule<Balance> {
+    /// emitted per session until the first decay
+    pub base_per_session: Balance,
+    /// first session that emits
+    pub start_session: SessionIndex,
+    /// sessions between decays, 0 keeps the base emission forever
+    pub decay_interval: SessionIndex,
+    /// part of the emission kept at every decay, 50% halves it
+    pub retained_per_interval: Perbill,
+    /// minting stops once the total issuance reaches this amount
+    pub max_total_issuance: Balance,
+}
+
+/// accounts allowed to run part of the pallet besides its admins
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub enum AdminRole {
+    /// registers the reward contract, sets its call limits and retries failed updates
+    ContractManager,
+    /// pauses reward distribution, resuming it is left to the admins
+    PauseGuardian,
+}
+
+/// how much of a node's session reward is withheld when it underperforms
+///
+/// in contract mode the node's votes in the list sent to the contract are cut by the same
+/// parts. the default withholds nothing.
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Default,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct PerformanceThresholds {
+    /// validators authoring fewer blocks keep the fraction of this minimum they did author
+    pub min_blocks_authored: u32,
+    /// withheld from validators that did not report themselves online
+    pub offline_penalty: Perbill,
+    /// withheld from nodes that were jailed at any point of the session
+    pub jailed_penalty: Perbill,
+}
+
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub enum WithholdReason {
+    TooFewBlocksAuthored,
+    Offline,
+    Jailed,
+}
+
+/// where native payouts of a session come from
+#[derive(PartialEq, Eq, Clone, Copy, RuntimeDebug)]
+pub enum PayoutSource {
+    /// transferred out of the reward pot
+    Pot,
+    /// minted by the emission schedule, only for what is actually paid
+    Emission,
+}
+
+/// where rewards withheld from nodes go
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub enum WithheldRewardsDestination {
+    /// left in the pot for later sessions
+    #[default]
+    Pot,
+    /// sent to the treasury account
+    Treasury,
+}
@@ -0,0 +1,816 @@
+use crate::{mock::*, *};
+use frame_support::{
+    assert_noop, assert_ok,
+    traits::{Currency, GetStorageVersion, Hooks, OnRuntimeUpgrade, StorageVersion},
+    weights::Weight,
+    BoundedVec,
+};
+use pallet_d9_node_voting::{
+    ContractEntry, ContractOperation, NodeMetadataStruct, NodeRewardManager, ValidatorDelegations,
+    ValidatorPerformance,
+};
+use pallet_session::SessionManager;
+use sp_runtime::{traits::BadOrigin, Perbill};
+use sp_staking::SessionIndex;
+
+fn submit_candidacy(candidate: u64, sharing_percent: u8) {
+    assert_ok!(NodeVoting::submit_candidacy(
+        RuntimeOrigin::signed(candidate),
+        NodeMetadataStruct {
+            name: BoundedVec::truncate_from(b"node".to_vec()),
+            sharing_percent,
+            index_of_last_percent_change: 0,
+            website: BoundedVec::default(),
+            contact: BoundedVec::default(),
+            region: BoundedVec::default(),
+            hardware: BoundedVec::default(),
+            commission_history: BoundedVec::default(),
+            session_keys: BoundedVec::default(),
+        }
+    ));
+}
+
+fn delegate(voter: u64, candidate: u64, votes: u64) {
+    assert_ok!(NodeVoting::purchase_voting_interest(
+        RuntimeOrigin::signed(voter),
+        voter,
+        votes
+    ));
+    assert_ok!(NodeVoting::delegate_votes(
+        RuntimeOrigin::signed(voter),
+        vec![ValidatorDelegations { candidate, votes }]
+    ));
+}
+
+fn pay_natively(reward_per_session: u64) {
+    assert_ok!(NodeRewards::set_reward_distribution_mode(
+        RuntimeOrigin::root(),
+        RewardDistributionMode::Native
+    ));
+    assert_ok!(NodeRewards::set_reward_per_session(
+        RuntimeOrigin::root(),
+        reward_per_session
+    ));
+}
+
+fn set_emission(base_per_session: u64, max_total_issuance: u64) {
+    assert_ok!(NodeRewards::set_emission_schedule(
+        RuntimeOrigin::root(),
+        Some(EmissionSchedule {
+            base_per_session,
+            start_session: 0,
+            decay_interval: 0,
+            retained_per_interval: Perbill::one(),
+            max_total_issuance,
+        })
+    ));
+}
+
+fn end_session(session: SessionIndex, nodes: Vec<(u64, u64)>) {
+    NodeRewards::update_rewards(session, nodes, vec![]);
+}
+
+fn performance(blocks_authored: u32, heartbeat_received: bool) -> ValidatorPerformance {
+    ValidatorPerformance {
+        blocks_authored,
+        heartbeat_received,
+    }
+}
+
+fn penalise_jails(jailed_penalty: Perbill) {
+    assert_ok!(NodeRewards::set_performance_thresholds(
+        RuntimeOrigin::root(),
+        PerformanceThresholds {
+            min_blocks_authored: 0,
+            offline_penalty: Perbill::zero(),
+            jailed_penalty,
+        }
+    ));
+}
+
+fn queued_attempts(session: SessionIndex) -> Option<u32> {
+    FailedRewardUpdates::<Test>::get(session).map(|update| update.attempts)
+}
+
+fn rank_by_tier() {
+    assert_ok!(NodeRewards::set_tier_cutoffs(
+        RuntimeOrigin::root(),
+        TierCutoffs {
+            upper_super: 1,
+            middle_super: 2,
+            lower_super: 3,
+            standby: 4,
+        }
+    ));
+}
+
+#[test]
+fn integrity_test_passes() {
+    new_test_ext().execute_with(|| {
+        <NodeRewards as Hooks<u64>>::integrity_test();
+    });
+}
+
+#[test]
+fn session_reward_is_split_by_tier() {
+    new_test_ext().execute_with(|| {
+        rank_by_tier();
+        pay_natively(1_600);
+        end_session(1, vec![(20, 50), (21, 40), (22, 30), (23, 20), (24, 10)]);
+
+        assert_eq!(Balances::free_balance(20), 600);
+        assert_eq!(Balances::free_balance(21), 500);
+        assert_eq!(Balances::free_balance(22), 400);
+        assert_eq!(Balances::free_balance(23), 100);
+        assert_eq!(Balances::free_balance(24), 0);
+        assert_eq!(NodeRewards::pot_balance(), POT_FUNDS - 1_600);
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::RewardsDistributed(
+            1, 1_600,
+        )));
+    });
+}
+
+#[test]
+fn tier_weights_are_set_by_governance() {
+    new_test_ext().execute_with(|| {
+        let weights = |candidate| TierWeights {
+            upper_super: 1,
+            middle_super: 1,
+            lower_super: 1,
+            standby: 1,
+            candidate,
+        };
+        assert_noop!(
+            NodeRewards::set_tier_weights(
+                RuntimeOrigin::root(),
+                TierWeights {
+                    upper_super: 0,
+                    middle_super: 0,
+                    lower_super: 0,
+                    standby: 0,
+                    candidate: 0,
+                }
+            ),
+            Error::<Test>::TierWeightsAllZero
+        );
+        assert_noop!(
+            NodeRewards::set_tier_weights(RuntimeOrigin::signed(ALICE), weights(0)),
+            BadOrigin
+        );
+        assert_ok!(NodeRewards::set_tier_weights(
+            RuntimeOrigin::root(),
+            weights(0)
+        ));
+        assert_eq!(NodeRewards::tier_weights(), weights(0));
+
+        rank_by_tier();
+        pay_natively(1_600);
+        end_session(1, vec![(20, 50), (21, 40), (22, 30), (23, 20), (24, 10)]);
+        for node in 20..24 {
+            assert_eq!(Balances::free_balance(node), 400);
+        }
+        assert_eq!(Balances::free_balance(24), 0);
+    });
+}
+
+#[test]
+fn rewards_are_capped_by_the_pot() {
+    new_test_ext().execute_with(|| {
+        pay_natively(2 * POT_FUNDS);
+        end_session(1, vec![(ALICE, 0)]);
+
+        // the pot keeps its existential deposit
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::RewardPotInsufficient(
+            1,
+            POT_FUNDS - 1,
+        )));
+        assert_eq!(NodeRewards::pot_balance(), 1);
+    });
+}
+
+#[test]
+fn tier_cutoffs_are_validated() {
+    new_test_ext().execute_with(|| {
+        let cutoffs = |lower_super, standby| TierCutoffs {
+            upper_super: 1,
+            middle_super: 2,
+            lower_super,
+            standby,
+        };
+        assert_noop!(
+            NodeRewards::set_tier_cutoffs(RuntimeOrigin::root(), cutoffs(5, 4)),
+            Error::<Test>::TierCutoffsNotAscending
+        );
+        assert_noop!(
+            NodeRewards::set_tier_cutoffs(RuntimeOrigin::root(), cutoffs(3, 11)),
+            Error::<Test>::TooManyRewardedNodes
+        );
+        assert_noop!(
+            NodeRewards::set_tier_cutoffs(RuntimeOrigin::signed(ALICE), cutoffs(3, 4)),
+            BadOrigin
+        );
+    });
+}
+
+#[test]
+fn nodes_are_tiered_by_their_rank() {
+    new_test_ext().execute_with(|| {
+        rank_by_tier();
+        end_session(1, vec![(20, 50), (21, 40), (22, 30), (23, 20), (24, 10)]);
+
+        assert_eq!(
+            NodeRewards::node_tier(20),
+            Some(NodeTier::Super(SuperNodeSubTier::Upper))
+        );
+        assert_eq!(
+            NodeRewards::node_tier(21),
+            Some(NodeTier::Super(SuperNodeSubTier::Middle))
+        );
+        assert_eq!(
+            NodeRewards::node_tier(22),
+            Some(NodeTier::Super(SuperNodeSubTier::Lower))
+        );
+        assert_eq!(NodeRewards::node_tier(23), Some(NodeTier::StandBy));
+        assert_eq!(NodeRewards::node_tier(24), Some(NodeTier::Candidate));
+
+        // a node that drops out of the ranking loses its tier
+        end_session(2, vec![(21, 40), (20, 30)]);
+        assert_eq!(NodeRewards::node_tier(22), None);
+        assert_eq!(
+            NodeRewards::node_tier(21),
+            Some(NodeTier::Super(SuperNodeSubTier::Upper))
+        );
+        assert_eq!(
+            NodeRewards::node_tier_history(21).into_inner(),
+            vec![
+                (1, NodeTier::Super(SuperNodeSubTier::Middle)),
+                (2, NodeTier::Super(SuperNodeSubTier::Upper)),
+            ]
+        );
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::NodeTierChanged(
+            22,
+            2,
+            Some(NodeTier::Super(SuperNodeSubTier::Lower)),
+            None,
+        )));
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::NodeTierChanged(
+            21,
+            2,
+            Some(NodeTier::Super(SuperNodeSubTier::Middle)),
+            Some(NodeTier::Super(SuperNodeSubTier::Upper)),
+        )));
+    });
+}
+
+#[test]
+fn supporters_claim_their_share_of_the_node_reward() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 50);
+        delegate(BOB, ALICE, 100);
+        pay_natively(1_000);
+        let alice_balance = Balances::free_balance(ALICE);
+        let bob_balance = Balances::free_balance(BOB);
+
+        end_session(1, NodeVoting::get_sorted_candidates_with_votes());
+        assert_eq!(Balances::free_balance(ALICE), alice_balance + 500);
+        assert_eq!(NodeRewards::total_unclaimed_rewards(), 500);
+        assert_eq!(NodeRewards::pending_rewards(&BOB), 500);
+
+        assert_ok!(NodeRewards::claim_rewards(RuntimeOrigin::signed(BOB)));
+        assert_eq!(Balances::free_balance(BOB), bob_balance + 500);
+        assert_eq!(NodeRewards::total_unclaimed_rewards(), 0);
+        assert_eq!(NodeRewards::pot_balance(), POT_FUNDS - 1_000);
+        assert_noop!(
+            NodeRewards::claim_rewards(RuntimeOrigin::signed(BOB)),
+            Error::<Test>::NoRewardsToClaim
+        );
+    });
+}
+
+#[test]
+fn delegation_change_settles_the_rewards_earned_so_far() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 50);
+        delegate(BOB, ALICE, 100);
+        pay_natively(1_000);
+        end_session(1, NodeVoting::get_sorted_candidates_with_votes());
+
+        assert_ok!(NodeVoting::try_remove_votes_from_candidate(
+            RuntimeOrigin::signed(BOB),
+            ALICE,
+            50
+        ));
+        assert_eq!(NodeRewards::unclaimed_rewards(BOB), 500);
+
+        // the remaining 50 votes are all the node's support, so they earn the whole share
+        end_session(2, NodeVoting::get_sorted_candidates_with_votes());
+        assert_eq!(NodeRewards::pending_rewards(&BOB), 1_000);
+    });
+}
+
+#[test]
+fn ledger_entries_go_with_delegations_and_candidates() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 50);
+        delegate(BOB, ALICE, 100);
+        delegate(CHARLIE, ALICE, 100);
+        pay_natively(1_000);
+        end_session(1, NodeVoting::get_sorted_candidates_with_votes());
+
+        // a supporter that withdraws everything keeps what it earned, not its snapshot
+        assert_ok!(NodeVoting::try_remove_votes_from_candidate(
+            RuntimeOrigin::signed(BOB),
+            ALICE,
+            100
+        ));
+        assert_eq!(NodeRewards::unclaimed_rewards(BOB), 250);
+        assert!(!SupporterRewardSnapshots::<Test>::contains_key(ALICE, BOB));
+        assert!(SupporterRewardSnapshots::<Test>::contains_key(
+            ALICE, CHARLIE
+        ));
+
+        // a candidate that leaves takes its reward per vote and the snapshots with it
+        assert_ok!(NodeVoting::remove_candidacy(RuntimeOrigin::signed(ALICE)));
+        NodeVoting::start_session(2);
+        assert_eq!(NodeRewards::unclaimed_rewards(CHARLIE), 250);
+        assert!(!RewardPerVote::<Test>::contains_key(ALICE));
+        assert_eq!(
+            SupporterRewardSnapshots::<Test>::iter_prefix(ALICE).count(),
+            0
+        );
+    });
+}
+
+#[test]
+fn failed_contract_update_is_retried_with_backoff() {
+    new_test_ext().execute_with(|| {
+        // no reward contract is registered, so every call fails
+        end_session(1, vec![(ALICE, 10)]);
+        assert_eq!(queued_attempts(1), Some(1));
+        assert_eq!(NodeRewards::number_of_failed_reward_updates(), 1);
+
+        NodeRewards::on_idle(2, Weight::MAX);
+        assert_eq!(queued_attempts(1), Some(1));
+        NodeRewards::on_idle(3, Weight::MAX);
+        assert_eq!(queued_attempts(1), Some(2));
+        // the delay doubles after a failed retry
+        NodeRewards::on_idle(6, Weight::MAX);
+        assert_eq!(queued_attempts(1), Some(2));
+        NodeRewards::on_idle(7, Weight::MAX);
+        assert_eq!(queued_attempts(1), None);
+        assert_eq!(NodeRewards::number_of_failed_reward_updates(), 0);
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::RewardUpdateAbandoned(1)));
+    });
+}
+
+#[test]
+fn full_retry_queue_drops_the_oldest_update() {
+    new_test_ext().execute_with(|| {
+        end_session(1, vec![(ALICE, 10)]);
+        end_session(2, vec![(ALICE, 10)]);
+        end_session(3, vec![(ALICE, 10)]);
+        assert_eq!(queued_attempts(1), None);
+        assert_eq!(queued_attempts(2), Some(1));
+        assert_eq!(queued_attempts(3), Some(1));
+        assert_eq!(NodeRewards::number_of_failed_reward_updates(), 2);
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::RewardUpdateDropped(1)));
+    });
+}
+
+#[test]
+fn contract_manager_retries_updates() {
+    new_test_ext().execute_with(|| {
+        end_session(1, vec![(ALICE, 10)]);
+        assert_ok!(NodeRewards::set_role(
+            RuntimeOrigin::root(),
+            AdminRole::ContractManager,
+            Some(DAVE)
+        ));
+        assert_noop!(
+            NodeRewards::retry_reward_update(RuntimeOrigin::signed(CHARLIE), 1),
+            BadOrigin
+        );
+        assert_noop!(
+            NodeRewards::retry_reward_update(RuntimeOrigin::signed(DAVE), 2),
+            Error::<Test>::NoFailedRewardUpdate
+        );
+        assert_ok!(NodeRewards::retry_reward_update(
+            RuntimeOrigin::signed(DAVE),
+            1
+        ));
+        assert_eq!(queued_attempts(1), Some(2));
+    });
+}
+
+#[test]
+fn paused_sessions_are_kept_until_rewards_resume() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(NodeRewards::pause_rewards(RuntimeOrigin::root()));
+        // more sessions than the retry queue holds, none of them is dropped
+        end_session(1, vec![(ALICE, 10)]);
+        end_session(2, vec![(ALICE, 10)]);
+        end_session(3, vec![(ALICE, 10)]);
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::RewardDistributionSkipped(
+            1,
+        )));
+        assert_eq!(NodeRewards::number_of_paused_reward_updates(), 3);
+        assert_eq!(NodeRewards::number_of_failed_reward_updates(), 0);
+
+        // nothing is sent until the rewards are resumed
+        NodeRewards::on_idle(10, Weight::MAX);
+        assert_eq!(NodeRewards::number_of_paused_reward_updates(), 3);
+        assert_ok!(NodeRewards::resume_rewards(RuntimeOrigin::root()));
+        NodeRewards::on_idle(1, Weight::MAX);
+        assert_eq!(NodeRewards::number_of_paused_reward_updates(), 0);
+        for session in 1..=3 {
+            System::assert_has_event(RuntimeEvent::NodeRewards(Event::RewardUpdateAttempted(
+                session, 1, false,
+            )));
+        }
+        // the sessions the contract rejected are retried like any failed update
+        assert_eq!(queued_attempts(2), Some(1));
+        assert_eq!(queued_attempts(3), Some(1));
+    });
+}
+
+#[test]
+fn contract_call_limits_are_capped_by_the_runtime() {
+    new_test_ext().execute_with(|| {
+        let limits = ContractCallLimits {
+            gas_limit: Weight::from_parts(1_000_000_000, 64 * 1024),
+            storage_deposit_limit: Some(100),
+        };
+        assert_noop!(
+            NodeRewards::set_contract_call_limits(RuntimeOrigin::signed(CHARLIE), limits.clone()),
+            BadOrigin
+        );
+        assert_noop!(
+            NodeRewards::set_contract_call_limits(
+                RuntimeOrigin::root(),
+                ContractCallLimits {
+                    gas_limit: MaxRewardUpdateGas::get().saturating_add(Weight::from_parts(1, 0)),
+                    storage_deposit_limit: None,
+                }
+            ),
+            Error::<Test>::ContractGasLimitTooHigh
+        );
+
+        assert_ok!(NodeRewards::set_contract_call_limits(
+            RuntimeOrigin::root(),
+            limits.clone()
+        ));
+        assert_eq!(NodeRewards::contract_call_limits(), limits);
+        System::assert_last_event(RuntimeEvent::NodeRewards(Event::ContractCallLimitsUpdated(
+            limits,
+        )));
+    });
+}
+
+#[test]
+fn reward_contract_is_managed_by_the_contract_manager() {
+    new_test_ext().execute_with(|| {
+        let entry = ContractEntry {
+            address: CHARLIE,
+            selector: ContractOperation::UpdateRewards.legacy_selector(),
+            abi_version: 1,
+        };
+        assert_noop!(
+            NodeRewards::set_reward_contract(RuntimeOrigin::signed(DAVE), None, vec![]),
+            BadOrigin
+        );
+        assert_ok!(NodeRewards::set_role(
+            RuntimeOrigin::root(),
+            AdminRole::ContractManager,
+            Some(DAVE)
+        ));
+        // the entry has to respond to a dry run, and there is no contract at the address
+        assert_noop!(
+            NodeRewards::set_reward_contract(RuntimeOrigin::signed(DAVE), Some(entry), vec![]),
+            pallet_d9_node_voting::Error::<Test>::ContractDryRunFailed
+        );
+        assert_ok!(NodeRewards::set_reward_contract(
+            RuntimeOrigin::signed(DAVE),
+            None,
+            vec![]
+        ));
+        System::assert_last_event(RuntimeEvent::NodeVoting(
+            pallet_d9_node_voting::Event::ContractDeregistered(ContractOperation::UpdateRewards),
+        ));
+    });
+}
+
+#[test]
+fn legacy_reward_contract_setter_goes_through_the_registry() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            NodeRewards::set_node_reward_contract(RuntimeOrigin::signed(DAVE), CHARLIE),
+            BadOrigin
+        );
+        assert_noop!(
+            NodeRewards::set_node_reward_contract(RuntimeOrigin::root(), CHARLIE),
+            pallet_d9_node_voting::Error::<Test>::ContractDryRunFailed
+        );
+        assert_eq!(
+            NodeVoting::contract_entry(ContractOperation::UpdateRewards),
+            None
+        );
+    });
+}
+
+#[test]
+fn emission_decays_per_interval() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(NodeRewards::set_emission_schedule(
+            RuntimeOrigin::root(),
+            Some(EmissionSchedule {
+                base_per_session: 1_000,
+                start_session: 2,
+                decay_interval: 2,
+                retained_per_interval: Perbill::from_percent(50),
+                max_total_issuance: u64::MAX,
+            })
+        ));
+        assert_eq!(NodeRewards::scheduled_emission(1), 0);
+        assert_eq!(NodeRewards::scheduled_emission(2), 1_000);
+        assert_eq!(NodeRewards::scheduled_emission(3), 1_000);
+        assert_eq!(NodeRewards::scheduled_emission(4), 500);
+        assert_eq!(NodeRewards::scheduled_emission(6), 250);
+    });
+}
+
+#[test]
+fn emission_stops_at_the_issuance_cap() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 0);
+        pay_natively(0);
+        let issuance = Balances::total_issuance();
+        set_emission(1_000, issuance + 600);
+        let alice_balance = Balances::free_balance(ALICE);
+
+        end_session(1, vec![(ALICE, 0)]);
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::EmissionCapReached(1, 400)));
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::RewardsMinted(
+            1,
+            600,
+            issuance + 600,
+        )));
+        assert_eq!(Balances::free_balance(ALICE), alice_balance + 600);
+        assert_eq!(Balances::total_issuance(), issuance + 600);
+        assert_eq!(NodeRewards::pot_balance(), POT_FUNDS);
+
+        end_session(2, vec![(ALICE, 0)]);
+        assert_eq!(Balances::free_balance(ALICE), alice_balance + 600);
+        assert_eq!(Balances::total_issuance(), issuance + 600);
+    });
+}
+
+#[test]
+fn only_the_paid_out_emission_is_minted() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 0);
+        pay_natively(0);
+        set_emission(1_000, u64::MAX);
+        assert_ok!(NodeRewards::set_performance_thresholds(
+            RuntimeOrigin::root(),
+            PerformanceThresholds {
+                min_blocks_authored: 0,
+                offline_penalty: Perbill::zero(),
+                jailed_penalty: Perbill::from_percent(50),
+            }
+        ));
+        assert_ok!(NodeVoting::jail_candidate(RuntimeOrigin::root(), ALICE, 1));
+        let issuance = Balances::total_issuance();
+
+        // the withheld half stays in the pot, so it is not minted
+        end_session(0, vec![(ALICE, 0)]);
+        assert_eq!(Balances::total_issuance(), issuance + 500);
+        assert_eq!(NodeRewards::pot_balance(), POT_FUNDS);
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::RewardsMinted(
+            0,
+            500,
+            issuance + 500,
+        )));
+    });
+}
+
+#[test]
+fn emission_is_paid_without_funds_in_the_pot() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 50);
+        delegate(BOB, ALICE, 100);
+        pay_natively(0);
+        set_emission(1_000, u64::MAX);
+        Balances::make_free_balance_be(&pot(), 0);
+        let alice_balance = Balances::free_balance(ALICE);
+        let issuance = Balances::total_issuance();
+
+        end_session(1, NodeVoting::get_sorted_candidates_with_votes());
+        assert_eq!(Balances::free_balance(ALICE), alice_balance + 500);
+        // the supporters' half is minted into the pot, which holds it until it is claimed
+        assert_eq!(NodeRewards::pot_balance(), 500);
+        assert_eq!(NodeRewards::pending_rewards(&BOB), 500);
+        assert_eq!(Balances::total_issuance(), issuance + 1_000);
+    });
+}
+
+#[test]
+fn contract_mode_mints_nothing() {
+    new_test_ext().execute_with(|| {
+        set_emission(1_000, u64::MAX);
+        let issuance = Balances::total_issuance();
+        end_session(1, vec![(ALICE, 10)]);
+        assert_eq!(Balances::total_issuance(), issuance);
+        assert_eq!(NodeRewards::pot_balance(), POT_FUNDS);
+    });
+}
+
+#[test]
+fn earnings_are_kept_for_the_history_depth() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 0);
+        pay_natively(100);
+        for session in 1..=3 {
+            end_session(session, vec![(ALICE, 0)]);
+        }
+
+        assert_eq!(NodeRewards::session_earnings(1, ALICE), 0);
+        assert_eq!(NodeRewards::oldest_earnings_session(), 2);
+        assert_eq!(
+            NodeRewards::earnings_between(&ALICE, 0, 10),
+            vec![(2, 100), (3, 100)]
+        );
+    });
+}
+
+#[test]
+fn upgrade_starts_the_earnings_history_at_the_current_session() {
+    new_test_ext().execute_with(|| {
+        StorageVersion::new(1).put::<NodeRewards>();
+        pallet_d9_node_voting::CurrentSessionIndex::<Test>::put(5);
+
+        migration::v2::MigrateToV2::<Test>::on_runtime_upgrade();
+        assert_eq!(NodeRewards::oldest_earnings_session(), 5);
+        assert_eq!(NodeRewards::on_chain_storage_version(), 2);
+    });
+}
+
+#[test]
+fn admins_are_set_by_the_admin_origin() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            NodeRewards::set_pallet_admin(RuntimeOrigin::signed(DAVE), DAVE),
+            BadOrigin
+        );
+        assert_ok!(NodeRewards::set_pallet_admin(RuntimeOrigin::root(), DAVE));
+        System::assert_last_event(RuntimeEvent::NodeRewards(Event::PalletAdminUpdated(DAVE)));
+
+        // the pallet admin can hand out roles, role holders cannot
+        assert_ok!(NodeRewards::set_role(
+            RuntimeOrigin::signed(DAVE),
+            AdminRole::PauseGuardian,
+            Some(CHARLIE)
+        ));
+        System::assert_last_event(RuntimeEvent::NodeRewards(Event::RoleUpdated(
+            AdminRole::PauseGuardian,
+            Some(CHARLIE),
+        )));
+        assert_noop!(
+            NodeRewards::set_role(
+                RuntimeOrigin::signed(CHARLIE),
+                AdminRole::ContractManager,
+                Some(CHARLIE)
+            ),
+            BadOrigin
+        );
+        assert_noop!(
+            NodeRewards::set_reward_per_session(RuntimeOrigin::signed(CHARLIE), 1),
+            BadOrigin
+        );
+    });
+}
+
+#[test]
+fn pause_guardian_pauses_and_admins_resume() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            NodeRewards::pause_rewards(RuntimeOrigin::signed(CHARLIE)),
+            BadOrigin
+        );
+        assert_ok!(NodeRewards::set_role(
+            RuntimeOrigin::root(),
+            AdminRole::PauseGuardian,
+            Some(CHARLIE)
+        ));
+        assert_ok!(NodeRewards::pause_rewards(RuntimeOrigin::signed(CHARLIE)));
+        assert_noop!(
+            NodeRewards::pause_rewards(RuntimeOrigin::signed(CHARLIE)),
+            Error::<Test>::DistributionPaused
+        );
+        assert_noop!(
+            NodeRewards::resume_rewards(RuntimeOrigin::signed(CHARLIE)),
+            BadOrigin
+        );
+        assert_noop!(
+            NodeRewards::claim_rewards(RuntimeOrigin::signed(BOB)),
+            Error::<Test>::DistributionPaused
+        );
+
+        assert_ok!(NodeRewards::set_pallet_admin(RuntimeOrigin::root(), DAVE));
+        assert_ok!(NodeRewards::resume_rewards(RuntimeOrigin::signed(DAVE)));
+        assert!(!NodeRewards::is_paused());
+        assert_noop!(
+            NodeRewards::resume_rewards(RuntimeOrigin::signed(DAVE)),
+            Error::<Test>::NotPaused
+        );
+    });
+}
+
+#[test]
+fn paused_sessions_are_not_paid_natively() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 0);
+        pay_natively(1_000);
+        let alice_balance = Balances::free_balance(ALICE);
+        assert_ok!(NodeRewards::pause_rewards(RuntimeOrigin::root()));
+        end_session(1, vec![(ALICE, 0)]);
+        assert_eq!(Balances::free_balance(ALICE), alice_balance);
+        assert_eq!(NodeRewards::pot_balance(), POT_FUNDS);
+        assert_eq!(NodeRewards::number_of_paused_reward_updates(), 0);
+    });
+}
+
+#[test]
+fn underperforming_nodes_have_rewards_withheld() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 0);
+        pay_natively(1_000);
+        assert_ok!(NodeRewards::set_performance_thresholds(
+            RuntimeOrigin::root(),
+            PerformanceThresholds {
+                min_blocks_authored: 10,
+                offline_penalty: Perbill::from_percent(50),
+                jailed_penalty: Perbill::from_percent(50),
+            }
+        ));
+        assert_ok!(NodeRewards::set_withheld_rewards_destination(
+            RuntimeOrigin::root(),
+            WithheldRewardsDestination::Treasury
+        ));
+        assert_ok!(NodeVoting::jail_candidate(RuntimeOrigin::root(), ALICE, 1));
+        let alice_balance = Balances::free_balance(ALICE);
+
+        // half for the blocks, then half of the rest for each of the other two reasons
+        NodeRewards::update_rewards(0, vec![(ALICE, 0)], vec![(ALICE, performance(5, false))]);
+        let withheld = |reason, amount| {
+            RuntimeEvent::NodeRewards(Event::RewardWithheld(0, ALICE, reason, amount))
+        };
+        System::assert_has_event(withheld(WithholdReason::TooFewBlocksAuthored, 500));
+        System::assert_has_event(withheld(WithholdReason::Offline, 250));
+        System::assert_has_event(withheld(WithholdReason::Jailed, 125));
+        assert_eq!(Balances::free_balance(ALICE), alice_balance + 125);
+        assert_eq!(Balances::free_balance(TREASURY), 875);
+        // the treasury transfer counts as paid out
+        System::assert_has_event(RuntimeEvent::NodeRewards(Event::RewardsDistributed(
+            0, 1_000,
+        )));
+    });
+}
+
+#[test]
+fn only_the_sessions_of_a_jail_are_penalised() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 0);
+        pay_natively(1_000);
+        penalise_jails(Perbill::from_percent(50));
+        assert_ok!(NodeVoting::jail_candidate(RuntimeOrigin::root(), ALICE, 1));
+        let alice_balance = Balances::free_balance(ALICE);
+
+        end_session(0, vec![(ALICE, 0)]);
+        assert_eq!(Balances::free_balance(ALICE), alice_balance + 500);
+
+        // the jail is over once the next session starts
+        NodeVoting::start_session(1);
+        end_session(1, vec![(ALICE, 0)]);
+        assert_eq!(Balances::free_balance(ALICE), alice_balance + 1_500);
+    });
+}
+
+#[test]
+fn contract_mode_sends_the_votes_unchanged() {
+    new_test_ext().execute_with(|| {
+        submit_candidacy(ALICE, 0);
+        penalise_jails(Perbill::from_percent(50));
+        assert_ok!(NodeVoting::jail_candidate(RuntimeOrigin::root(), ALICE, 1));
+
+        end_session(0, vec![(ALICE, 800), (BOB, 600)]);
+        let queued = FailedRewardUpdates::<Test>::get(0).unwrap();
+        assert_eq!(
+            queued.sorted_nodes.into_inner(),
+            vec![(ALICE, 800), (BOB, 600)]
+        );
+        assert!(!System::events().iter().any(|record| matches!(
+            record.event,
+            RuntimeEvent::NodeRewards(Event::RewardWithheld(..))
+        )));
+    });
+}
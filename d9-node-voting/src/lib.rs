@@ -2,10 +2,16 @@
 use sp_staking::SessionIndex;
 use sp_std::prelude::*;
 mod structs;
-use frame_support::traits::Currency;
+use frame_support::traits::{Currency, ReservableCurrency};
+pub mod migration;
+pub mod runtime_api;
 pub use pallet::*;
-use sp_arithmetic::Perquintill;
+use sp_arithmetic::{Perbill, Perquintill};
 pub use structs::*;
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
 
 pub type BalanceOf<T> = <<T as pallet_contracts::Config>::Currency as Currency<
     <T as frame_system::Config>::AccountId,
@@ -16,6 +22,8 @@ pub mod pallet {
     use frame_support::{
         inherent::Vec,
         pallet_prelude::{DispatchResult, OptionQuery, ValueQuery, *},
+        storage::with_transaction,
+        traits::{Contains, ExistenceRequirement, WithdrawReasons},
         weights::Weight,
         Blake2_128Concat, BoundedVec,
     };
@@ -24,12 +32,19 @@ pub mod pallet {
         ensure_root,
         pallet_prelude::{OriginFor, *},
     };
+    use sp_runtime::traits::BadOrigin;
 
     use pallet_session::SessionManager;
-    use sp_runtime::Saturating;
+    use sp_runtime::{traits::Zero, Saturating, TransactionOutcome};
+    use sp_staking::offence::{DisableStrategy, OffenceDetails, OnOffenceHandler};
+    use sp_std::collections::btree_map::BTreeMap;
 
     const STORAGE_VERSION: frame_support::traits::StorageVersion =
-        frame_support::traits::StorageVersion::new(1);
+        frame_support::traits::StorageVersion::new(4);
+    /// upper bound on archived sessions removed in one go, so a backlog is cleared gradually
+    const MAX_SESSIONS_PRUNED_PER_CALL: u32 = 16;
+    /// votes every genesis candidate starts with
+    pub const BOOTSTRAP_VOTES: u64 = 1000;
     #[pallet::pallet]
     #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
@@ -38,15 +53,64 @@ pub mod pallet {
     pub trait Config: frame_system::Config + pallet_contracts::Config {
         type CurrencySubUnits: Get<BalanceOf<Self>>;
 
-        type Currency: Currency<Self::AccountId>;
+        type Currency: ReservableCurrency<Self::AccountId, Balance = BalanceOf<Self>>;
 
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 
         type MaxCandidates: Get<u32>;
 
+        /// hard cap on the validator set, the governance set maximum cannot exceed it
         type MaxValidatorNodes: Get<u32>;
 
         type NodeRewardManager: NodeRewardManager<Self::AccountId>;
+
+        /// maximum number of candidates a single delegation policy may split votes between
+        #[pallet::constant]
+        type MaxDelegationPolicyEntries: Get<u32>;
+
+        /// liveness of the validators, reported with their performance at the end of a session
+        type ValidatorHeartbeats: ValidatorHeartbeats<Self::AccountId>;
+
+        /// number of past sessions kept in the session archive and node lists, at least 1
+        #[pallet::constant]
+        type HistoryDepth: Get<SessionIndex>;
+
+        /// sessions between scheduling a sharing percentage change and it taking effect
+        #[pallet::constant]
+        type SharePercentNoticePeriod: Get<SessionIndex>;
+
+        /// largest decrease of a sharing percentage allowed in a single change
+        #[pallet::constant]
+        type MaxSharePercentDrop: Get<u8>;
+
+        /// most gas the registered burn contract hook may use for one purchase of voting interest
+        #[pallet::constant]
+        type MaxBurnContractGas: Get<Weight>;
+
+        /// sessions in one conviction lock period
+        #[pallet::constant]
+        type ConvictionPeriod: Get<SessionIndex>;
+
+        /// reserved from a candidate for storing its metadata
+        #[pallet::constant]
+        type MetadataDepositBase: Get<BalanceOf<Self>>;
+
+        /// reserved from a candidate for every byte of its encoded metadata
+        #[pallet::constant]
+        type MetadataDepositPerByte: Get<BalanceOf<Self>>;
+
+        /// accounts whose session keys are registered, only these can be elected
+        type SessionKeysRegistered: Contains<Self::AccountId>;
+
+        /// sessions between a candidate announcing its retirement and its removal
+        ///
+        /// with 0 the candidate is removed when the next session starts
+        #[pallet::constant]
+        type RetirementNoticePeriod: Get<SessionIndex>;
+
+        /// sessions a candidate is excluded from elections after an offence
+        #[pallet::constant]
+        type JailDuration: Get<SessionIndex>;
     }
 
     /// defines the voting power of a user
@@ -86,13 +150,51 @@ pub mod pallet {
         QueryKind = ValueQuery,
     >;
 
-    /// grand total of votes for a candidate
+    /// conviction and decay state of a delegation
+    ///
+    /// candidate -> supporter -> weight
+    /// delegations without an entry count with their raw votes
+    #[pallet::storage]
+    #[pallet::getter(fn delegation_weights)]
+    pub type DelegationWeights<T: Config> = StorageNMap<
+        Key = (
+            NMapKey<Blake2_128Concat, T::AccountId>,
+            NMapKey<Blake2_128Concat, T::AccountId>,
+        ),
+        Value = DelegationWeight,
+        QueryKind = OptionQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn vote_decay)]
+    pub type VoteDecay<T: Config> = StorageValue<_, VoteDecayParameters, ValueQuery>;
+
+    /// raw key of the last delegation visited by an unfinished decay sweep
+    #[pallet::storage]
+    pub type DecaySweepCursor<T: Config> =
+        StorageValue<_, BoundedVec<u8, ConstU32<256>>, OptionQuery>;
+
+    /// session whose decay sweep has completed
+    #[pallet::storage]
+    #[pallet::getter(fn last_decay_sweep_session)]
+    pub type LastDecaySweepSession<T: Config> = StorageValue<_, SessionIndex, ValueQuery>;
+
+    /// grand total of effective votes for a candidate
     ///
     /// this Map can be no larger than MaxCandidates
     #[pallet::storage]
     #[pallet::getter(fn node_votes)]
     pub type NodeAccumulativeVotes<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, u64, OptionQuery>;
+
+    /// votes a genesis candidate was started with
+    ///
+    /// they are part of the candidate's total but belong to no voter, so they cannot be moved
+    /// or withdrawn and leave with the candidate
+    #[pallet::storage]
+    #[pallet::getter(fn bootstrap_votes)]
+    pub type BootstrapVotes<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, u64, ValueQuery>;
     #[pallet::storage]
     #[pallet::getter(fn total_number_of_candidate_nodes)]
     pub type CurrentNumberOfCandidatesNodes<T: Config> = StorageValue<_, u32, ValueQuery>;
@@ -107,6 +209,73 @@ pub mod pallet {
         OptionQuery,
     >;
 
+    /// candidates that are not active, a missing entry means active
+    #[pallet::storage]
+    #[pallet::getter(fn candidate_status)]
+    pub type CandidateStatuses<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, CandidateStatus, ValueQuery>;
+
+    /// latest session a candidate was jailed in, at any point of it
+    #[pallet::storage]
+    #[pallet::getter(fn last_jailed_session)]
+    pub type LastJailedSession<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, SessionIndex, OptionQuery>;
+
+    /// account that manages a candidate's metadata and supporter share
+    ///
+    /// stash -> controller
+    /// CandidateControllers == ControllerToStash
+    #[pallet::storage]
+    #[pallet::getter(fn candidate_controller)]
+    pub type CandidateControllers<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+    /// controller -> stash
+    #[pallet::storage]
+    #[pallet::getter(fn controller_stash)]
+    pub type ControllerToStash<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+    /// account managing a user's delegations on the user's behalf
+    ///
+    /// owner -> manager
+    #[pallet::storage]
+    #[pallet::getter(fn vote_manager)]
+    pub type VoteManagers<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, VoteManager<T::AccountId>, OptionQuery>;
+
+    /// votes moved by managed calls on behalf of an owner, and the session they were moved in
+    ///
+    /// owner -> (session, votes)
+    #[pallet::storage]
+    #[pallet::getter(fn managed_votes_moved)]
+    pub type ManagedVotesMoved<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, (SessionIndex, u64), ValueQuery>;
+
+    /// amount currently reserved from a candidate for its metadata
+    #[pallet::storage]
+    #[pallet::getter(fn node_metadata_deposit)]
+    pub type NodeMetadataDeposits<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
+
+    /// elected validators of past sessions with their vote stats and sharing percentages
+    ///
+    /// only the last `HistoryDepth` sessions are retained
+    #[pallet::storage]
+    #[pallet::getter(fn session_archive)]
+    pub type SessionArchive<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        SessionIndex,
+        BoundedVec<ArchivedValidator<T::AccountId>, T::MaxValidatorNodes>,
+        OptionQuery,
+    >;
+
+    /// oldest session index whose history has not been pruned yet
+    #[pallet::storage]
+    #[pallet::getter(fn oldest_retained_session)]
+    pub type OldestRetainedSession<T: Config> = StorageValue<_, SessionIndex, ValueQuery>;
+
     #[pallet::storage]
     #[pallet::getter(fn current_session_index)]
     pub type CurrentSessionIndex<T: Config> = StorageValue<_, SessionIndex, ValueQuery>;
@@ -121,16 +290,175 @@ pub mod pallet {
     pub type NodeMetadata<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AccountId, NodeMetadataStruct, OptionQuery>;
 
+    /// sharing percentage changes waiting for their notice period to pass
+    #[pallet::storage]
+    #[pallet::getter(fn pending_share_percent_change)]
+    pub type PendingSharePercentChanges<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, PendingSharePercentChange, OptionQuery>;
+
+    /// optional burn contract that voting interest purchases are routed through
+    ///
+    /// the purchase calls the `BurnForVotes` contract of the registry with this contract.
+    /// when unset the pallet handles the tokens itself according to `NativeBurnDestination`
+    #[pallet::storage]
+    #[pallet::getter(fn burn_contract_hook)]
+    pub type BurnContractHook<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
+
+    /// contracts the runtime calls, by the operation they perform
+    #[pallet::storage]
+    #[pallet::getter(fn contract_entry)]
+    pub type ContractRegistry<T: Config> =
+        StorageMap<_, Twox64Concat, ContractOperation, ContractEntry<T::AccountId>, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn native_burn_destination)]
+    pub type NativeBurnDestination<T: Config> =
+        StorageValue<_, BurnDestination<T::AccountId>, ValueQuery>;
+
+    #[pallet::type_value]
+    pub fn DefaultMinValidatorNodes<T: Config>() -> u32 {
+        1
+    }
+
+    /// fewest validators a new set may have, below it the current set is kept
+    #[pallet::storage]
+    #[pallet::getter(fn min_validator_nodes)]
+    pub type MinValidatorNodes<T: Config> =
+        StorageValue<_, u32, ValueQuery, DefaultMinValidatorNodes<T>>;
+
+    #[pallet::type_value]
+    pub fn DefaultMaxValidatorNodes<T: Config>() -> u32 {
+        T::MaxValidatorNodes::get()
+    }
+
+    #[pallet::storage]
+    #[pallet::getter(fn max_validator_nodes)]
+    pub type MaxValidatorNodes<T: Config> =
+        StorageValue<_, u32, ValueQuery, DefaultMaxValidatorNodes<T>>;
+
+    /// nodes that are part of every validator set
+    ///
+    /// they bypass the vote ranking but not the safety checks: a jailed node or one without
+    /// session keys is left out like any other candidate
+    #[pallet::storage]
+    #[pallet::getter(fn invulnerable_nodes)]
+    pub type InvulnerableNodes<T: Config> =
+        StorageValue<_, BoundedVec<T::AccountId, T::MaxValidatorNodes>, ValueQuery>;
+
+    /// set while too few nodes qualify and the validator set is frozen
+    #[pallet::storage]
+    #[pallet::getter(fn emergency_mode)]
+    pub type EmergencyMode<T: Config> = StorageValue<_, bool, ValueQuery>;
+
+    /// validator set most recently handed to pallet-session
+    #[pallet::storage]
+    #[pallet::getter(fn selected_validators)]
+    pub type SelectedValidators<T: Config> =
+        StorageValue<_, BoundedVec<T::AccountId, T::MaxValidatorNodes>, ValueQuery>;
+
+    #[pallet::type_value]
+    pub fn DefaultMinimumBurnAmount<T: Config>() -> BalanceOf<T> {
+        <BalanceOf<T>>::from(100u32).saturating_mul(T::CurrencySubUnits::get())
+    }
+
+    /// smallest amount that can be spent on voting interest in one purchase
+    #[pallet::storage]
+    #[pallet::getter(fn minimum_burn_amount)]
+    pub type MinimumBurnAmount<T: Config> =
+        StorageValue<_, BalanceOf<T>, ValueQuery, DefaultMinimumBurnAmount<T>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn voting_interest_pricing)]
+    pub type VotingInterestPricingCurve<T: Config> =
+        StorageValue<_, VotingInterestPricing<BalanceOf<T>>, ValueQuery>;
+
+    /// lifetime amount an account has paid for voting interest, used by the pricing tiers
+    ///
+    /// tracked per payer, so buying votes for others moves the payer through the tiers and
+    /// a third party cannot push a voter into a cheaper tier
+    #[pallet::storage]
+    #[pallet::getter(fn voting_interest_spent)]
+    pub type VotingInterestSpent<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
+
     #[pallet::storage]
     #[pallet::getter(fn pallet_admin)]
     pub type PalletAdmin<T: Config> = StorageValue<_, T::AccountId, OptionQuery>;
 
+    /// percentage split used to automatically delegate a user's newly acquired votes
+    ///
+    /// user -> [(candidate, share)]
+    #[pallet::storage]
+    #[pallet::getter(fn delegation_policy)]
+    pub type DelegationPolicies<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        BoundedVec<DelegationPolicyEntry<T>, T::MaxDelegationPolicyEntries>,
+        OptionQuery,
+    >;
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         CandidacySubmitted(T::AccountId),
         VotesDelegatedBy(T::AccountId),
         CandidacyRemoved(T::AccountId),
+        DelegationPolicySet(T::AccountId),
+        DelegationPolicyCleared(T::AccountId),
+        /// votes freed from a removed candidate were redistributed by the supporter's policy
+        /// [supporter, removed_candidate, votes]
+        FreedVotesRebalanced(T::AccountId, T::AccountId, u64),
+        /// supporters are warned of an upcoming sharing percentage change
+        /// [candidate, current_percent, new_percent, effective_session]
+        SupporterShareChangeScheduled(T::AccountId, u8, u8, SessionIndex),
+        SupporterShareChangeCancelled(T::AccountId),
+        /// [candidate, sharing_percent]
+        SupporterShareChanged(T::AccountId, u8),
+        /// [token_burner, beneficiary_voter, amount_burned, votes_added]
+        VotingInterestAdded(T::AccountId, T::AccountId, BalanceOf<T>, u64),
+        BurnContractHookUpdated(Option<T::AccountId>),
+        NativeBurnDestinationUpdated(BurnDestination<T::AccountId>),
+        VotingInterestPricingUpdated,
+        MinimumBurnAmountUpdated(BalanceOf<T>),
+        /// [voter, candidate, conviction, locked_until]
+        DelegationLocked(T::AccountId, T::AccountId, Conviction, SessionIndex),
+        DelegationsRefreshed(T::AccountId),
+        VoteDecayUpdated(VoteDecayParameters),
+        CandidateInfoUpdated(T::AccountId),
+        /// [candidate, deposit]
+        MetadataDepositUpdated(T::AccountId, BalanceOf<T>),
+        /// [stash, controller]
+        ControllerSet(T::AccountId, T::AccountId),
+        ControllerRemoved(T::AccountId),
+        /// [owner, manager]
+        VoteManagerSet(T::AccountId, T::AccountId),
+        VoteManagerRevoked(T::AccountId),
+        /// [manager, owner, votes]
+        ManagedVotesMoved(T::AccountId, T::AccountId, u64),
+        /// [voter, number_of_moves, votes_moved]
+        VotesRedistributed(T::AccountId, u32, u64),
+        /// [min, max]
+        ValidatorSetBoundsUpdated(u32, u32),
+        InvulnerableNodesUpdated(Vec<T::AccountId>),
+        /// too few nodes qualified for session `session_index`, the current set is kept
+        /// [session_index, qualified_nodes]
+        ValidatorSetFrozen(SessionIndex, u32),
+        EmergencyModeExited(SessionIndex),
+        CandidateChilled(T::AccountId),
+        CandidateUnchilled(T::AccountId),
+        /// [candidate, until]
+        CandidateJailed(T::AccountId, SessionIndex),
+        CandidateUnjailed(T::AccountId),
+        /// [candidate, effective_session]
+        CandidateRetiring(T::AccountId, SessionIndex),
+        /// [candidates, delegations]
+        VoteTotalsRepaired(u32, u32),
+        /// [operation, entry]
+        ContractRegistered(ContractOperation, ContractEntry<T::AccountId>),
+        ContractDeregistered(ContractOperation),
+        /// [operation, address, responded]
+        ContractDryRun(ContractOperation, T::AccountId, bool),
     }
 
     #[pallet::error]
@@ -148,9 +476,43 @@ pub mod pallet {
         VoterDidntDelegateToThisCandidate,
         NotActiveValidator,
         AtMaximumNumberOfCandidates,
-        BurnAmountMustBeGreaterThan100,
+        BurnAmountBelowMinimum,
+        BurnContractMismatch,
         SupporterShareOutOfRange,
-        CurrentValidatorCanNotChangeSharePercentage,
+        SharePercentChangeAlreadyPending,
+        DelegationPolicyTooLarge,
+        DelegationPolicySharesMustTotal100,
+        DuplicateCandidateInDelegationPolicy,
+        NoDelegationPolicy,
+        SharePercentDropTooLarge,
+        NoPendingSharePercentChange,
+        PricingTiersNotAscending,
+        TimeMultipliersNotAscending,
+        DelegationLocked,
+        CannotShortenDelegationLock,
+        InvalidConviction,
+        InsufficientBalanceForMetadataDeposit,
+        NotController,
+        ControllerAlreadyPaired,
+        AccountIsController,
+        NotVoteManager,
+        VoteManagerExpired,
+        ManagedVotesExceedLimit,
+        NoVoteManager,
+        EmptyVoteMoveList,
+        TooManyVoteMoves,
+        InvalidVoteMove,
+        InvalidValidatorSetBounds,
+        TooManyInvulnerableNodes,
+        CandidateNotActive,
+        CandidateNotChilled,
+        ContractNotRegistered,
+        ContractDryRunFailed,
+        /// the operation's contract is registered through the pallet that calls it
+        ContractManagedElsewhere,
+        InsufficientBalanceToBurn,
+        TooManyDelegations,
+        CannotAddToLockedDelegation,
     }
 
     #[pallet::genesis_config]
@@ -162,7 +524,8 @@ pub mod pallet {
     impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
         fn build(&self) {
             for candidate in self.initial_candidates.iter() {
-                NodeAccumulativeVotes::<T>::insert(candidate.clone(), 1000);
+                NodeAccumulativeVotes::<T>::insert(candidate.clone(), BOOTSTRAP_VOTES);
+                BootstrapVotes::<T>::insert(candidate.clone(), BOOTSTRAP_VOTES);
                 CurrentNumberOfCandidatesNodes::<T>::put(
                     CurrentNumberOfCandidatesNodes::<T>::get() + 1,
                 );
@@ -179,6 +542,25 @@ pub mod pallet {
         }
     }
 
+    #[pallet::hooks]
+    impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+        fn on_idle(_n: T::BlockNumber, remaining_weight: Weight) -> Weight {
+            Self::sweep_delegation_weights(remaining_weight)
+        }
+
+        fn integrity_test() {
+            assert!(
+                T::MaxBurnContractGas::get().all_lt(T::BlockWeights::get().max_block),
+                "MaxBurnContractGas has to leave room in the block"
+            );
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn try_state(_n: T::BlockNumber) -> Result<(), &'static str> {
+            Self::do_try_state()
+        }
+    }
+
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         #[pallet::call_index(0)]
@@ -192,135 +574,126 @@ pub mod pallet {
             if candidate_votes_opt.is_some() {
                 return Err(Error::<T>::CandidateAlreadyExists.into());
             }
+            if ControllerToStash::<T>::contains_key(candidate_node.clone()) {
+                return Err(Error::<T>::AccountIsController.into());
+            }
             let current_candidate_count = CurrentNumberOfCandidatesNodes::<T>::get();
             let max_candidates = T::MaxCandidates::get();
             if current_candidate_count + 1 > max_candidates {
                 return Err(Error::<T>::AtMaximumNumberOfCandidates.into());
             }
 
-            NodeAccumulativeVotes::<T>::insert(candidate_node.clone(), 0);
             let current_index = CurrentSessionIndex::<T>::get();
-            CurrentNumberOfCandidatesNodes::<T>::put(current_candidate_count + 1);
-            let node_metadata: NodeMetadataStruct = NodeMetadataStruct {
-                name: candidate_metadata.name.clone(),
-                sharing_percent: candidate_metadata.sharing_percent.clone(),
+            let mut node_metadata: NodeMetadataStruct = NodeMetadataStruct {
                 index_of_last_percent_change: current_index,
+                commission_history: BoundedVec::default(),
+                ..candidate_metadata
             };
+            node_metadata.record_commission_change(current_index, node_metadata.sharing_percent);
+            Self::update_metadata_deposit(&candidate_node, &node_metadata)?;
+
+            NodeAccumulativeVotes::<T>::insert(candidate_node.clone(), 0);
+            CurrentNumberOfCandidatesNodes::<T>::put(current_candidate_count + 1);
             NodeMetadata::<T>::insert(candidate_node.clone(), node_metadata);
             Self::deposit_event(Event::CandidacySubmitted(candidate_node));
             Ok(())
         }
 
+        /// spend tokens to give `beneficiary_voter` voting interest through the burn contract
+        ///
+        /// deprecated, kept so existing callers keep working: use `purchase_voting_interest`.
+        /// `main_pool` and `burn_contract` are no longer trusted, they have to match the
+        /// registered `BurnForVotes` contract and burn contract hook.
         #[pallet::call_index(1)]
-        #[pallet::weight(T::DbWeight::get().reads_writes(5, 5))]
+        #[pallet::weight(
+            T::DbWeight::get()
+                .reads_writes(6, 5)
+                .saturating_add(T::MaxBurnContractGas::get())
+                .saturating_add(Pallet::<T>::reward_settlement_weight(
+                    T::MaxDelegationPolicyEntries::get() as u64
+                ))
+        )]
         pub fn add_voting_interest(
             origin: OriginFor<T>,
             beneficiary_voter: T::AccountId,
             main_pool: T::AccountId,
             amount_to_burn: BalanceOf<T>,
             burn_contract: T::AccountId,
-        ) -> DispatchResult {
+        ) -> DispatchResultWithPostInfo {
             let token_burner = ensure_signed(origin)?;
-            let weight = Weight::from_parts(50_000_000_000, 800_000);
-            Self::call_burn_contract(
-                token_burner,
-                beneficiary_voter.clone(),
-                main_pool,
-                amount_to_burn,
-                burn_contract,
-                weight,
-            )?;
-
-            let voting_interest_increase = Self::calculate_voting_interests(amount_to_burn);
-            Self::add_voting_interest_internal(beneficiary_voter, voting_interest_increase);
-            Ok(())
+            let registered_main_pool = ContractRegistry::<T>::get(ContractOperation::BurnForVotes)
+                .map(|entry| entry.address);
+            if registered_main_pool != Some(main_pool)
+                || BurnContractHook::<T>::get() != Some(burn_contract)
+            {
+                return Err(Error::<T>::BurnContractMismatch.into());
+            }
+            Self::do_add_voting_interest(token_burner, beneficiary_voter, amount_to_burn)
         }
 
         #[pallet::call_index(2)]
-        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        #[pallet::weight(
+            T::DbWeight::get()
+                .reads_writes(1, 1)
+                .saturating_add(Pallet::<T>::reward_settlement_weight(delegations.len() as u64))
+        )]
         pub fn delegate_votes(
             origin: OriginFor<T>,
             delegations: Vec<ValidatorDelegations<T>>,
         ) -> DispatchResult {
             let delegator = ensure_signed(origin)?;
-            if delegations.len() == 0 {
-                return Err(Error::<T>::EmptyDelegationList.into());
-            }
-            if (delegations.len() as u32) > T::MaxCandidates::get() {
-                return Err(Error::<T>::DelegationListTooLarge.into());
-            }
-
-            let maybe_voting_interest = UsersVotingInterests::<T>::get(delegator.clone());
-            if maybe_voting_interest.is_none() {
-                return Err(Error::<T>::DelegatorHasNoVotingCapacity.into());
-            }
-            let voting_interest = maybe_voting_interest.unwrap();
-            Self::validate_delegations(&voting_interest, &delegations)?;
-            let _ = Self::delegate_votes_to_candidates(&delegator, delegations);
-            Self::deposit_event(Event::VotesDelegatedBy(delegator));
-            Ok(())
+            Self::do_delegate_votes(delegator, delegations)
         }
 
+        /// announce the caller's retirement, it is removed once the notice period has passed
+        ///
+        /// the removal always happens when a session starts, so this call stays cheap however
+        /// many supporters the candidate has
         #[pallet::call_index(3)]
-        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 1))]
         pub fn remove_candidacy(origin: OriginFor<T>) -> DispatchResult {
             let candidate: T::AccountId = ensure_signed(origin)?;
-            let mut support_to_remove: Vec<(T::AccountId, u64)> = Vec::new();
-            let mut prefix_iterator = NodeToUserVotesTotals::<T>::iter_prefix((candidate.clone(),));
-            while let Some((supporter, delegated_votes)) = prefix_iterator.next() {
-                support_to_remove.push((supporter, delegated_votes));
-            }
-            for support in support_to_remove {
-                Self::remove_votes_from_candidate(&support.0, &candidate, support.1);
+            if !Self::is_valid_candidate(&candidate) {
+                return Err(Error::<T>::CandidateDoesNotExist.into());
             }
-            let current_candidate_count = CurrentNumberOfCandidatesNodes::<T>::get();
-            CurrentNumberOfCandidatesNodes::<T>::put(current_candidate_count - 1);
-            NodeMetadata::<T>::remove(candidate.clone());
-            Self::deposit_event(Event::CandidacyRemoved(candidate));
+            // supporters get the notice period to move their votes elsewhere
+            let effective =
+                CurrentSessionIndex::<T>::get().saturating_add(T::RetirementNoticePeriod::get());
+            CandidateStatuses::<T>::insert(
+                candidate.clone(),
+                CandidateStatus::Retiring { effective },
+            );
+            Self::deposit_event(Event::CandidateRetiring(candidate, effective));
             Ok(())
         }
 
         #[pallet::call_index(4)]
-        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        #[pallet::weight(
+            T::DbWeight::get()
+                .reads_writes(1, 1)
+                .saturating_add(Pallet::<T>::reward_settlement_weight(1))
+        )]
         pub fn try_remove_votes_from_candidate(
             origin: OriginFor<T>,
             candidate: T::AccountId,
             votes: u64,
         ) -> DispatchResult {
             let voter = ensure_signed(origin)?;
-            if !Self::is_valid_candidate(&candidate) {
-                return Err(Error::<T>::CandidateDoesNotExist.into());
-            }
-            let delegated_votes =
-                UserToNodeVotesTotals::<T>::get((voter.clone(), candidate.clone()));
-            if delegated_votes == 0 {
-                return Err(Error::<T>::VoterDidntDelegateToThisCandidate.into());
-            }
-            if votes > delegated_votes {
-                return Err(Error::<T>::AttemptingToRemoveMoreVotesThanDelegated.into());
-            }
-
-            Self::remove_votes_from_candidate(&voter, &candidate, votes);
-            Ok(())
+            Self::do_remove_votes(voter, candidate, votes)
         }
         #[pallet::call_index(5)]
-        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        #[pallet::weight(
+            T::DbWeight::get()
+                .reads_writes(1, 1)
+                .saturating_add(Pallet::<T>::reward_settlement_weight(2))
+        )]
         pub fn redistribute_votes(
             origin: OriginFor<T>,
             from: T::AccountId,
             to: T::AccountId,
         ) -> DispatchResult {
             let voter = ensure_signed(origin)?;
-            if !Self::is_valid_candidate(&to) || !Self::is_valid_candidate(&from) {
-                return Err(Error::<T>::CandidateDoesNotExist.into());
-            }
-            let delegated_votes = UserToNodeVotesTotals::<T>::get((voter.clone(), from.clone()));
-            if delegated_votes == 0 {
-                return Err(Error::<T>::VoterDidntDelegateToThisCandidate.into());
-            }
-            Self::remove_votes_from_candidate(&voter, &from, delegated_votes);
-            Self::add_votes_to_candidate(&voter, &to, delegated_votes);
-            Ok(())
+            Self::do_redistribute_votes(voter, from, to)
         }
 
         #[pallet::call_index(6)]
@@ -329,7 +702,7 @@ pub mod pallet {
             origin: OriginFor<T>,
             name: BoundedVec<u8, ConstU32<128>>,
         ) -> DispatchResult {
-            let origin = ensure_signed(origin)?;
+            let origin = Self::ensure_candidate_controller(origin)?;
             if !Self::is_valid_candidate(&origin) {
                 return Err(Error::<T>::CandidateDoesNotExist.into());
             }
@@ -339,12 +712,18 @@ pub mod pallet {
             }
             let mut node_metadata = node_metadata_option.unwrap();
             node_metadata.name = name.clone();
+            Self::update_metadata_deposit(&origin, &node_metadata)?;
             NodeMetadata::<T>::insert(origin.clone(), node_metadata);
             Ok(())
         }
 
+        /// schedule a new supporter share for the caller's node
+        ///
+        /// the change takes effect `SharePercentNoticePeriod` sessions from now, which gives
+        /// supporters time to move their votes elsewhere. a single change may not lower the
+        /// share by more than `MaxSharePercentDrop` points.
         #[pallet::call_index(7)]
-        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        #[pallet::weight(T::DbWeight::get().reads_writes(4, 1))]
         pub fn change_candidate_supporter_share(
             origin: OriginFor<T>,
             sharing_percent: u8,
@@ -353,26 +732,37 @@ pub mod pallet {
                 return Err(Error::<T>::SupporterShareOutOfRange.into());
             }
 
-            let node_id = ensure_signed(origin)?;
+            let node_id = Self::ensure_candidate_controller(origin)?;
             if !Self::is_valid_candidate(&node_id) {
                 return Err(Error::<T>::CandidateDoesNotExist.into());
             }
-
-            if CurrentValidatorVoteStats::<T>::contains_key(node_id.clone()) {
-                return Err(Error::<T>::CurrentValidatorCanNotChangeSharePercentage.into());
+            if PendingSharePercentChanges::<T>::contains_key(node_id.clone()) {
+                return Err(Error::<T>::SharePercentChangeAlreadyPending.into());
             }
             let node_metadata_option = NodeMetadata::<T>::get(node_id.clone());
             if node_metadata_option.is_none() {
                 return Err(Error::<T>::ErrorGettingNodeMetadata.into());
             }
-            let mut node_metadata = node_metadata_option.unwrap();
-            let current_index = CurrentSessionIndex::<T>::get();
-            if node_metadata.index_of_last_percent_change > current_index.saturating_sub(3) {
-                return Err(Error::<T>::CurrentValidatorCanNotChangeSharePercentage.into());
+            let current_percent = node_metadata_option.unwrap().sharing_percent;
+            if current_percent.saturating_sub(sharing_percent) > T::MaxSharePercentDrop::get() {
+                return Err(Error::<T>::SharePercentDropTooLarge.into());
             }
-            node_metadata.sharing_percent = sharing_percent.clone();
-            node_metadata.index_of_last_percent_change = current_index;
-            NodeMetadata::<T>::insert(node_id.clone(), node_metadata);
+
+            let effective_session =
+                CurrentSessionIndex::<T>::get().saturating_add(T::SharePercentNoticePeriod::get());
+            PendingSharePercentChanges::<T>::insert(
+                node_id.clone(),
+                PendingSharePercentChange {
+                    sharing_percent,
+                    effective_session,
+                },
+            );
+            Self::deposit_event(Event::SupporterShareChangeScheduled(
+                node_id,
+                current_percent,
+                sharing_percent,
+                effective_session,
+            ));
             Ok(())
         }
 
@@ -392,72 +782,683 @@ pub mod pallet {
             PalletAdmin::<T>::put(new_admin);
             Ok(())
         }
-    }
 
-    impl<T: Config> Pallet<T> {
-        /// add voting interest to a user (for vote delegation)
+        /// store a percentage split that newly acquired votes are delegated by
         ///
-        /// * `delegator` - the user to add voting interest to
-        /// * `votes` - the number of votes to add
-        pub fn add_voting_interest_internal(
-            delegator: T::AccountId,
-            voting_interest_increase: u64,
-        ) {
+        /// shares must total 100%. votes that are currently undelegated are distributed
+        /// immediately; later voting interest is distributed as it is added. unlike explicit
+        /// delegations, votes the policy adds to a locked delegation join the running lock.
+        #[pallet::call_index(9)]
+        #[pallet::weight(
+            T::DbWeight::get().reads_writes(4, 4).saturating_add(
+                Pallet::<T>::reward_settlement_weight(T::MaxDelegationPolicyEntries::get() as u64)
+            )
+        )]
+        pub fn set_delegation_policy(
+            origin: OriginFor<T>,
+            policy: Vec<DelegationPolicyEntry<T>>,
+        ) -> DispatchResult {
+            let delegator = ensure_signed(origin)?;
+            if policy.len() == 0 {
+                return Err(Error::<T>::EmptyDelegationList.into());
+            }
+            let bounded_policy: BoundedVec<
+                DelegationPolicyEntry<T>,
+                T::MaxDelegationPolicyEntries,
+            > = BoundedVec::try_from(policy).map_err(|_| Error::<T>::DelegationPolicyTooLarge)?;
+            Self::validate_delegation_policy(&bounded_policy)?;
+            DelegationPolicies::<T>::insert(delegator.clone(), bounded_policy);
+
             let voting_interest =
-                UsersVotingInterests::<T>::mutate(delegator.clone(), |voting_interest_opt| {
-                    let voting_interest = voting_interest_opt
-                        .clone()
-                        .unwrap_or(VotingInterest::default());
-                    let new_total = voting_interest
-                        .total
-                        .saturating_add(voting_interest_increase);
-                    VotingInterest {
-                        total: new_total,
-                        delegated: voting_interest.delegated,
-                    }
-                });
-            UsersVotingInterests::<T>::insert(delegator, voting_interest);
+                UsersVotingInterests::<T>::get(delegator.clone()).unwrap_or_default();
+            let available_votes = voting_interest
+                .total
+                .saturating_sub(voting_interest.delegated);
+            Self::apply_delegation_policy(&delegator, available_votes);
+            Self::deposit_event(Event::DelegationPolicySet(delegator));
+            Ok(())
         }
 
-        pub fn get_sorted_candidates_with_votes() -> Vec<(T::AccountId, u64)> {
-            let mut candidates =
-                NodeAccumulativeVotes::<T>::iter().collect::<Vec<(T::AccountId, u64)>>();
-            candidates.sort_by(|a, b| b.1.cmp(&a.1));
-            let mut sorted_candidates =
-                candidates.into_iter().collect::<Vec<(T::AccountId, u64)>>();
-
-            sorted_candidates.truncate(T::MaxCandidates::get() as usize);
-            sorted_candidates
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn clear_delegation_policy(origin: OriginFor<T>) -> DispatchResult {
+            let delegator = ensure_signed(origin)?;
+            if !DelegationPolicies::<T>::contains_key(delegator.clone()) {
+                return Err(Error::<T>::NoDelegationPolicy.into());
+            }
+            DelegationPolicies::<T>::remove(delegator.clone());
+            Self::deposit_event(Event::DelegationPolicyCleared(delegator));
+            Ok(())
         }
 
-        pub fn get_user_supported_nodes(delegator: T::AccountId) -> Vec<T::AccountId> {
-            let delegatees_plus_votes =
-                UserToNodeVotesTotals::<T>::iter_prefix((delegator.clone(),))
-                    .collect::<Vec<(T::AccountId, u64)>>();
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn cancel_supporter_share_change(origin: OriginFor<T>) -> DispatchResult {
+            let node_id = Self::ensure_candidate_controller(origin)?;
+            if PendingSharePercentChanges::<T>::take(node_id.clone()).is_none() {
+                return Err(Error::<T>::NoPendingSharePercentChange.into());
+            }
+            Self::deposit_event(Event::SupporterShareChangeCancelled(node_id));
+            Ok(())
+        }
 
-            let delegatees = delegatees_plus_votes
-                .into_iter()
-                .map(|(delegatee, _)| delegatee)
-                .collect();
+        /// register or clear the burn contract that voting interest purchases are routed through
+        ///
+        /// the main pool it is passed to is the `BurnForVotes` contract of the registry
+        #[pallet::call_index(12)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 1))]
+        pub fn set_burn_contract_hook(
+            origin: OriginFor<T>,
+            hook: Option<T::AccountId>,
+        ) -> DispatchResult {
+            Self::root_or_admin(origin)?;
+            match hook.clone() {
+                Some(burn_contract) => {
+                    if ContractRegistry::<T>::get(ContractOperation::BurnForVotes).is_none() {
+                        return Err(Error::<T>::ContractNotRegistered.into());
+                    }
+                    BurnContractHook::<T>::put(burn_contract)
+                }
+                None => BurnContractHook::<T>::kill(),
+            }
+            Self::deposit_event(Event::BurnContractHookUpdated(hook));
+            Ok(())
+        }
 
-            delegatees
+        #[pallet::call_index(13)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_native_burn_destination(
+            origin: OriginFor<T>,
+            destination: BurnDestination<T::AccountId>,
+        ) -> DispatchResult {
+            Self::root_or_admin(origin)?;
+            NativeBurnDestination::<T>::put(destination.clone());
+            Self::deposit_event(Event::NativeBurnDestinationUpdated(destination));
+            Ok(())
         }
 
-        pub fn get_node_sharing_percent(node_id: T::AccountId) -> Option<u8> {
-            let node_metadata = NodeMetadata::<T>::get(node_id.clone());
-            if node_metadata.is_none() {
-                return None;
+        #[pallet::call_index(14)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_voting_interest_pricing(
+            origin: OriginFor<T>,
+            pricing: VotingInterestPricing<BalanceOf<T>>,
+        ) -> DispatchResult {
+            Self::root_or_admin(origin)?;
+            if pricing
+                .tiers
+                .windows(2)
+                .any(|pair| pair[0].threshold >= pair[1].threshold)
+            {
+                return Err(Error::<T>::PricingTiersNotAscending.into());
             }
-            let candidate_metadata = node_metadata.unwrap();
-            Some(candidate_metadata.sharing_percent)
+            if pricing
+                .time_multipliers
+                .windows(2)
+                .any(|pair| pair[0].until_session >= pair[1].until_session)
+            {
+                return Err(Error::<T>::TimeMultipliersNotAscending.into());
+            }
+            VotingInterestPricingCurve::<T>::put(pricing);
+            Self::deposit_event(Event::VotingInterestPricingUpdated);
+            Ok(())
         }
 
-        pub fn get_user_support_ratio(
+        #[pallet::call_index(15)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_minimum_burn_amount(
+            origin: OriginFor<T>,
+            amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            Self::root_or_admin(origin)?;
+            MinimumBurnAmount::<T>::put(amount);
+            Self::deposit_event(Event::MinimumBurnAmountUpdated(amount));
+            Ok(())
+        }
+
+        /// lock the caller's delegation to `candidate` for the periods of `conviction`
+        ///
+        /// a locked delegation counts `conviction` times its votes, does not decay and cannot
+        /// be removed until the lock expires. a running lock can only be extended.
+        #[pallet::call_index(16)]
+        #[pallet::weight(
+            T::DbWeight::get()
+                .reads_writes(4, 2)
+                .saturating_add(Pallet::<T>::reward_settlement_weight(1))
+        )]
+        pub fn lock_delegation(
+            origin: OriginFor<T>,
+            candidate: T::AccountId,
+            conviction: Conviction,
+        ) -> DispatchResult {
+            let voter = ensure_signed(origin)?;
+            if conviction.lock_periods() == 0 {
+                return Err(Error::<T>::InvalidConviction.into());
+            }
+            let delegated_votes =
+                UserToNodeVotesTotals::<T>::get((voter.clone(), candidate.clone()));
+            if delegated_votes == 0 {
+                return Err(Error::<T>::VoterDidntDelegateToThisCandidate.into());
+            }
+            let current_index = CurrentSessionIndex::<T>::get();
+            let locked_until = current_index.saturating_add(
+                conviction
+                    .lock_periods()
+                    .saturating_mul(T::ConvictionPeriod::get()),
+            );
+            if let Some(weight) = DelegationWeights::<T>::get((candidate.clone(), voter.clone())) {
+                if locked_until < weight.locked_until {
+                    return Err(Error::<T>::CannotShortenDelegationLock.into());
+                }
+            }
+
+            Self::sync_delegation_weight(&voter, &candidate, delegated_votes, |weight| {
+                weight.conviction = conviction;
+                weight.locked_until = locked_until;
+                weight.refreshed_at = current_index;
+            });
+            Self::deposit_event(Event::DelegationLocked(
+                voter,
+                candidate,
+                conviction,
+                locked_until,
+            ));
+            Ok(())
+        }
+
+        /// reset the decay of every delegation of the caller
+        ///
+        /// `max_delegations` bounds the weight of the call and must be at least the number of
+        /// candidates the caller delegates to.
+        #[pallet::call_index(17)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(
+            1u64.saturating_add(4u64.saturating_mul(*max_delegations as u64)),
+            2u64.saturating_mul(*max_delegations as u64),
+        ).saturating_add(Pallet::<T>::reward_settlement_weight(*max_delegations as u64)))]
+        pub fn refresh_delegations(origin: OriginFor<T>, max_delegations: u32) -> DispatchResult {
+            let voter = ensure_signed(origin)?;
+            let current_index = CurrentSessionIndex::<T>::get();
+            let delegations: Vec<(T::AccountId, u64)> =
+                UserToNodeVotesTotals::<T>::iter_prefix((voter.clone(),))
+                    .take((max_delegations as usize).saturating_add(1))
+                    .collect();
+            if delegations.len() > max_delegations as usize {
+                return Err(Error::<T>::TooManyDelegations.into());
+            }
+            for (candidate, delegated_votes) in delegations {
+                Self::sync_delegation_weight(&voter, &candidate, delegated_votes, |weight| {
+                    weight.refreshed_at = current_index;
+                });
+            }
+            Self::deposit_event(Event::DelegationsRefreshed(voter));
+            Ok(())
+        }
+
+        #[pallet::call_index(18)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_vote_decay(origin: OriginFor<T>, decay: VoteDecayParameters) -> DispatchResult {
+            Self::root_or_admin(origin)?;
+            VoteDecay::<T>::put(decay.clone());
+            Self::deposit_event(Event::VoteDecayUpdated(decay));
+            Ok(())
+        }
+
+        /// replace the descriptive fields of the caller's metadata
+        ///
+        /// the supporter share and commission history in `info` are ignored, the share is
+        /// changed through `change_candidate_supporter_share`. the metadata deposit is topped up
+        /// or partially returned to match the new size.
+        #[pallet::call_index(19)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 3))]
+        pub fn update_candidate_info(
+            origin: OriginFor<T>,
+            info: NodeMetadataStruct,
+        ) -> DispatchResult {
+            let candidate = Self::ensure_candidate_controller(origin)?;
+            if !Self::is_valid_candidate(&candidate) {
+                return Err(Error::<T>::CandidateDoesNotExist.into());
+            }
+            let node_metadata_option = NodeMetadata::<T>::get(candidate.clone());
+            if node_metadata_option.is_none() {
+                return Err(Error::<T>::ErrorGettingNodeMetadata.into());
+            }
+            let current_metadata = node_metadata_option.unwrap();
+            let node_metadata = NodeMetadataStruct {
+                sharing_percent: current_metadata.sharing_percent,
+                index_of_last_percent_change: current_metadata.index_of_last_percent_change,
+                commission_history: current_metadata.commission_history,
+                ..info
+            };
+            Self::update_metadata_deposit(&candidate, &node_metadata)?;
+            NodeMetadata::<T>::insert(candidate.clone(), node_metadata);
+            Self::deposit_event(Event::CandidateInfoUpdated(candidate));
+            Ok(())
+        }
+
+        /// let `controller` manage the caller's candidacy
+        ///
+        /// the caller stays the stash: it holds the deposit, is elected and is paid. passing
+        /// the stash itself as `controller` removes the current controller.
+        #[pallet::call_index(20)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(4, 3))]
+        pub fn set_controller(origin: OriginFor<T>, controller: T::AccountId) -> DispatchResult {
+            let stash = ensure_signed(origin)?;
+            if !NodeAccumulativeVotes::<T>::contains_key(stash.clone()) {
+                return Err(Error::<T>::CandidateDoesNotExist.into());
+            }
+            if controller != stash {
+                if NodeAccumulativeVotes::<T>::contains_key(controller.clone()) {
+                    return Err(Error::<T>::CandidateAlreadyExists.into());
+                }
+                let paired_stash = ControllerToStash::<T>::get(controller.clone());
+                if paired_stash.is_some() && paired_stash.unwrap() != stash {
+                    return Err(Error::<T>::ControllerAlreadyPaired.into());
+                }
+            }
+
+            if let Some(old_controller) = CandidateControllers::<T>::take(stash.clone()) {
+                ControllerToStash::<T>::remove(old_controller);
+            }
+            if controller == stash {
+                Self::deposit_event(Event::ControllerRemoved(stash));
+                return Ok(());
+            }
+            CandidateControllers::<T>::insert(stash.clone(), controller.clone());
+            ControllerToStash::<T>::insert(controller.clone(), stash.clone());
+            Self::deposit_event(Event::ControllerSet(stash, controller));
+            Ok(())
+        }
+
+        /// allow `manager` to delegate, remove and redistribute the caller's votes
+        ///
+        /// managed calls may move at most `max_votes_per_session` votes per session. the manager
+        /// can no longer act from session `expires_at` on. replaces any existing manager.
+        #[pallet::call_index(21)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(0, 1))]
+        pub fn set_vote_manager(
+            origin: OriginFor<T>,
+            manager: T::AccountId,
+            max_votes_per_session: u64,
+            expires_at: Option<SessionIndex>,
+        ) -> DispatchResult {
+            let owner = ensure_signed(origin)?;
+            VoteManagers::<T>::insert(
+                owner.clone(),
+                VoteManager {
+                    manager: manager.clone(),
+                    max_votes_per_session,
+                    expires_at,
+                },
+            );
+            Self::deposit_event(Event::VoteManagerSet(owner, manager));
+            Ok(())
+        }
+
+        #[pallet::call_index(22)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn revoke_vote_manager(origin: OriginFor<T>) -> DispatchResult {
+            let owner = ensure_signed(origin)?;
+            if VoteManagers::<T>::take(owner.clone()).is_none() {
+                return Err(Error::<T>::NoVoteManager.into());
+            }
+            Self::deposit_event(Event::VoteManagerRevoked(owner));
+            Ok(())
+        }
+
+        /// change the delegations of `owner` as its vote manager
+        ///
+        /// the votes moved count towards the manager's allowance for the current session
+        #[pallet::call_index(23)]
+        #[pallet::weight(
+            T::DbWeight::get()
+                .reads_writes(5, 5)
+                .saturating_add(Pallet::<T>::reward_settlement_weight(call.delegations_changed()))
+        )]
+        pub fn manage_votes_of(
+            origin: OriginFor<T>,
+            owner: T::AccountId,
+            call: ManagedVoteCall<T>,
+        ) -> DispatchResult {
+            let manager = ensure_signed(origin)?;
+            let vote_manager_opt = VoteManagers::<T>::get(owner.clone());
+            if vote_manager_opt.is_none() {
+                return Err(Error::<T>::NotVoteManager.into());
+            }
+            let vote_manager = vote_manager_opt.unwrap();
+            if vote_manager.manager != manager {
+                return Err(Error::<T>::NotVoteManager.into());
+            }
+            if let Some(expires_at) = vote_manager.expires_at {
+                if CurrentSessionIndex::<T>::get() >= expires_at {
+                    return Err(Error::<T>::VoteManagerExpired.into());
+                }
+            }
+
+            let votes_moved = match call.clone() {
+                ManagedVoteCall::DelegateVotes(delegations) => {
+                    delegations.iter().fold(0u64, |total, delegation| {
+                        total.saturating_add(delegation.votes)
+                    })
+                }
+                ManagedVoteCall::RemoveVotes { votes, .. } => votes,
+                ManagedVoteCall::RedistributeVotes { from, .. } => {
+                    UserToNodeVotesTotals::<T>::get((owner.clone(), from))
+                }
+                ManagedVoteCall::RedistributeVotesBatch(moves) => {
+                    moves.iter().fold(0u64, |total, vote_move| {
+                        total.saturating_add(vote_move.votes)
+                    })
+                }
+            };
+            let current_index = CurrentSessionIndex::<T>::get();
+            let (usage_session, moved_before) = ManagedVotesMoved::<T>::get(owner.clone());
+            let moved_before = if usage_session == current_index {
+                moved_before
+            } else {
+                0
+            };
+            let moved_total = moved_before.saturating_add(votes_moved);
+            if moved_total > vote_manager.max_votes_per_session {
+                return Err(Error::<T>::ManagedVotesExceedLimit.into());
+            }
+
+            match call {
+                ManagedVoteCall::DelegateVotes(delegations) => {
+                    Self::do_delegate_votes(owner.clone(), delegations)?
+                }
+                ManagedVoteCall::RemoveVotes { candidate, votes } => {
+                    Self::do_remove_votes(owner.clone(), candidate, votes)?
+                }
+                ManagedVoteCall::RedistributeVotes { from, to } => {
+                    Self::do_redistribute_votes(owner.clone(), from, to)?
+                }
+                ManagedVoteCall::RedistributeVotesBatch(moves) => {
+                    Self::do_redistribute_votes_batch(owner.clone(), moves)?
+                }
+            }
+            ManagedVotesMoved::<T>::insert(owner.clone(), (current_index, moved_total));
+            Self::deposit_event(Event::ManagedVotesMoved(manager, owner, votes_moved));
+            Ok(())
+        }
+
+        /// move parts of the caller's delegations between candidates in one go
+        ///
+        /// moves are applied in order, so votes moved to a candidate can be moved on by a later
+        /// entry. every move is checked before any is applied; if one fails nothing changes.
+        #[pallet::call_index(24)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(
+            8u64.saturating_mul(moves.len() as u64),
+            6u64.saturating_mul(moves.len() as u64),
+        ).saturating_add(Pallet::<T>::reward_settlement_weight(
+            2u64.saturating_mul(moves.len() as u64),
+        )))]
+        pub fn redistribute_votes_batch(
+            origin: OriginFor<T>,
+            moves: Vec<VoteMove<T>>,
+        ) -> DispatchResult {
+            let voter = ensure_signed(origin)?;
+            Self::do_redistribute_votes_batch(voter, moves)
+        }
+
+        /// set the governance bounds on the validator set size
+        ///
+        /// `max` cannot exceed the `MaxValidatorNodes` cap of the runtime
+        #[pallet::call_index(25)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(2, 2))]
+        pub fn set_validator_set_bounds(
+            origin: OriginFor<T>,
+            min: u32,
+            max: u32,
+        ) -> DispatchResult {
+            Self::root_or_admin(origin)?;
+            if min == 0 || min > max || max > T::MaxValidatorNodes::get() {
+                return Err(Error::<T>::InvalidValidatorSetBounds.into());
+            }
+            if (InvulnerableNodes::<T>::get().len() as u32) > max {
+                return Err(Error::<T>::TooManyInvulnerableNodes.into());
+            }
+            MinValidatorNodes::<T>::put(min);
+            MaxValidatorNodes::<T>::put(max);
+            Self::deposit_event(Event::ValidatorSetBoundsUpdated(min, max));
+            Ok(())
+        }
+
+        #[pallet::call_index(26)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(1, 1))]
+        pub fn set_invulnerable_nodes(
+            origin: OriginFor<T>,
+            nodes: Vec<T::AccountId>,
+        ) -> DispatchResult {
+            Self::root_or_admin(origin)?;
+            let mut nodes = nodes;
+            nodes.sort();
+            nodes.dedup();
+            if (nodes.len() as u32) > MaxValidatorNodes::<T>::get() {
+                return Err(Error::<T>::TooManyInvulnerableNodes.into());
+            }
+            let bounded_nodes: BoundedVec<T::AccountId, T::MaxValidatorNodes> =
+                BoundedVec::try_from(nodes.clone())
+                    .map_err(|_| Error::<T>::TooManyInvulnerableNodes)?;
+            InvulnerableNodes::<T>::put(bounded_nodes);
+            Self::deposit_event(Event::InvulnerableNodesUpdated(nodes));
+            Ok(())
+        }
+
+        /// stop the caller's node from being elected while keeping its votes
+        #[pallet::call_index(27)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 1))]
+        pub fn chill(origin: OriginFor<T>) -> DispatchResult {
+            let candidate = Self::ensure_candidate_controller(origin)?;
+            if !Self::is_valid_candidate(&candidate) {
+                return Err(Error::<T>::CandidateDoesNotExist.into());
+            }
+            if CandidateStatuses::<T>::get(candidate.clone()) != CandidateStatus::Active {
+                return Err(Error::<T>::CandidateNotActive.into());
+            }
+            CandidateStatuses::<T>::insert(candidate.clone(), CandidateStatus::Chilled);
+            Self::deposit_event(Event::CandidateChilled(candidate));
+            Ok(())
+        }
+
+        #[pallet::call_index(28)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 1))]
+        pub fn unchill(origin: OriginFor<T>) -> DispatchResult {
+            let candidate = Self::ensure_candidate_controller(origin)?;
+            if CandidateStatuses::<T>::get(candidate.clone()) != CandidateStatus::Chilled {
+                return Err(Error::<T>::CandidateNotChilled.into());
+            }
+            CandidateStatuses::<T>::remove(candidate.clone());
+            Self::deposit_event(Event::CandidateUnchilled(candidate));
+            Ok(())
+        }
+
+        /// exclude `candidate` from elections for `sessions` sessions
+        #[pallet::call_index(29)]
+        #[pallet::weight(T::DbWeight::get().reads_writes(3, 1))]
+        pub fn jail_candidate(
+            origin: OriginFor<T>,
+            candidate: T::AccountId,
+            sessions: SessionIndex,
+        ) -> DispatchResult {
+            Self::root_or_admin(origin)?;
+            if !Self::is_valid_candidate(&candidate) {
+                return Err(Error::<T>::CandidateDoesNotExist.into());
+            }
+            Self::jail(&candidate, sessions);
+            Ok(())
+        }
+
+        /// rebuild every derived vote total from `UserToNodeVotesTotals`
+        ///
+        /// delegations to candidates that no longer exist are dropped. candidate totals become
+        /// their bootstrap votes plus the sum of their delegations' effective weights.
+        #[pallet::call_index(30)]
+        #[pallet::weight(T::BlockWeights::get().max_block)]
+        pub fn repair_vote_totals(origin: OriginFor<T>) -> DispatchResult {
+            ensure_root(origin)?;
+            let (candidates, delegations) = Self::rebuild_vote_totals();
+            Self::deposit_event(Event::VoteTotalsRepaired(candidates, delegations));
+            Ok(())
+        }
+
+        /// point a contract operation at a new contract, or clear it
+        ///
+        /// a new entry is dry run with `call_args` first and only registered if the contract
+        /// responds without reverting. the node reward contract is managed by the reward
+        /// manager's own admins and cannot be set here.
+        #[pallet::call_index(31)]
+        #[pallet::weight(
+            T::DbWeight::get().reads_writes(3, 1).saturating_add(Pallet::<T>::dry_run_gas_limit())
+        )]
+        pub fn register_contract(
+            origin: OriginFor<T>,
+            operation: ContractOperation,
+            entry: Option<ContractEntry<T::AccountId>>,
+            call_args: Vec<u8>,
+        ) -> DispatchResult {
+            let caller = ensure_signed_or_root(origin.clone())?;
+            Self::root_or_admin(origin)?;
+            if operation == ContractOperation::UpdateRewards {
+                return Err(Error::<T>::ContractManagedElsewhere.into());
+            }
+            Self::do_register_contract(operation, entry, caller, call_args)
+        }
+
+        /// check that a contract responds to an operation's message without changing any state
+        ///
+        /// the outcome is reported in a `ContractDryRun` event
+        #[pallet::call_index(32)]
+        #[pallet::weight(
+            T::DbWeight::get().reads_writes(2, 0).saturating_add(Pallet::<T>::dry_run_gas_limit())
+        )]
+        pub fn dry_run_contract(
+            origin: OriginFor<T>,
+            operation: ContractOperation,
+            entry: ContractEntry<T::AccountId>,
+            call_args: Vec<u8>,
+        ) -> DispatchResult {
+            let caller = ensure_signed_or_root(origin.clone())?;
+            Self::root_or_admin(origin)?;
+            let dry_run_origin = caller.unwrap_or(entry.address.clone());
+            let responded = Self::dry_run_contract_call(&entry, dry_run_origin, call_args);
+            Self::deposit_event(Event::ContractDryRun(operation, entry.address, responded));
+            Ok(())
+        }
+
+        /// spend tokens to give `beneficiary_voter` voting interest
+        ///
+        /// the tokens are routed through the registered contract hook if there is one,
+        /// otherwise the pallet burns or transfers them itself. charged for
+        /// `MaxBurnContractGas`, the gas the hook did not use is refunded.
+        #[pallet::call_index(33)]
+        #[pallet::weight(
+            T::DbWeight::get()
+                .reads_writes(6, 5)
+                .saturating_add(T::MaxBurnContractGas::get())
+                .saturating_add(Pallet::<T>::reward_settlement_weight(
+                    T::MaxDelegationPolicyEntries::get() as u64
+                ))
+        )]
+        pub fn purchase_voting_interest(
+            origin: OriginFor<T>,
+            beneficiary_voter: T::AccountId,
+            amount_to_burn: BalanceOf<T>,
+        ) -> DispatchResultWithPostInfo {
+            let token_burner = ensure_signed(origin)?;
+            Self::do_add_voting_interest(token_burner, beneficiary_voter, amount_to_burn)
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// add voting interest to a user (for vote delegation)
+        ///
+        /// * `delegator` - the user to add voting interest to
+        /// * `votes` - the number of votes to add
+        pub fn add_voting_interest_internal(
+            delegator: T::AccountId,
+            voting_interest_increase: u64,
+        ) {
+            let voting_interest =
+                UsersVotingInterests::<T>::mutate(delegator.clone(), |voting_interest_opt| {
+                    let voting_interest = voting_interest_opt
+                        .clone()
+                        .unwrap_or(VotingInterest::default());
+                    let new_total = voting_interest
+                        .total
+                        .saturating_add(voting_interest_increase);
+                    VotingInterest {
+                        total: new_total,
+                        delegated: voting_interest.delegated,
+                    }
+                });
+            UsersVotingInterests::<T>::insert(delegator.clone(), voting_interest);
+            Self::apply_delegation_policy(&delegator, voting_interest_increase);
+        }
+
+        pub fn get_sorted_candidates_with_votes() -> Vec<(T::AccountId, u64)> {
+            let mut candidates =
+                NodeAccumulativeVotes::<T>::iter().collect::<Vec<(T::AccountId, u64)>>();
+            candidates.sort_by(|a, b| b.1.cmp(&a.1));
+            let mut sorted_candidates =
+                candidates.into_iter().collect::<Vec<(T::AccountId, u64)>>();
+
+            sorted_candidates.truncate(T::MaxCandidates::get() as usize);
+            sorted_candidates
+        }
+
+        /// candidates sorted by votes, skipping `offset` and returning at most `limit`
+        pub fn get_candidates_page(offset: u32, limit: u32) -> Vec<CandidateInfo<T::AccountId>> {
+            Self::get_sorted_candidates_with_votes()
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .map(|(account_id, total_votes)| CandidateInfo {
+                    metadata: NodeMetadata::<T>::get(account_id.clone()),
+                    status: CandidateStatuses::<T>::get(account_id.clone()),
+                    account_id,
+                    total_votes,
+                })
+                .collect()
+        }
+
+        /// validators that would be elected if the next session was planned now
+        pub fn get_projected_validators() -> Vec<T::AccountId> {
+            let sorted_candidates = Self::get_sorted_candidates().unwrap_or_default();
+            Self::select_validators(sorted_candidates)
+        }
+
+        pub fn get_session_archive(
+            session_index: SessionIndex,
+        ) -> Option<Vec<ArchivedValidator<T::AccountId>>> {
+            SessionArchive::<T>::get(session_index).map(|validators| validators.into_inner())
+        }
+
+        pub fn get_user_supported_nodes(delegator: T::AccountId) -> Vec<T::AccountId> {
+            let delegatees_plus_votes =
+                UserToNodeVotesTotals::<T>::iter_prefix((delegator.clone(),))
+                    .collect::<Vec<(T::AccountId, u64)>>();
+
+            let delegatees = delegatees_plus_votes
+                .into_iter()
+                .map(|(delegatee, _)| delegatee)
+                .collect();
+
+            delegatees
+        }
+
+        pub fn get_node_sharing_percent(node_id: T::AccountId) -> Option<u8> {
+            let node_metadata = NodeMetadata::<T>::get(node_id.clone());
+            if node_metadata.is_none() {
+                return None;
+            }
+            let candidate_metadata = node_metadata.unwrap();
+            Some(candidate_metadata.sharing_percent)
+        }
+
+        pub fn get_user_support_ratio(
             delegator: T::AccountId,
             candidate: T::AccountId,
         ) -> Option<Perquintill> {
-            let user_to_node_votes =
-                UserToNodeVotesTotals::<T>::get((delegator.clone(), candidate.clone()));
+            let user_to_node_votes = Self::delegation_weight(&delegator, &candidate);
             if user_to_node_votes == 0 {
                 return None;
             }
@@ -470,6 +1471,19 @@ pub mod pallet {
             Some(ratio)
         }
 
+        /// weight of the reward manager settling `settlements` delegations before they change
+        pub fn reward_settlement_weight(settlements: u64) -> Weight {
+            T::NodeRewardManager::delegation_weight_change_weight().saturating_mul(settlements)
+        }
+
+        /// weight a delegation currently adds to the candidate's accumulated votes
+        pub fn delegation_weight(delegator: &T::AccountId, candidate: &T::AccountId) -> u64 {
+            match DelegationWeights::<T>::get((candidate.clone(), delegator.clone())) {
+                Some(weight) => weight.effective_votes,
+                None => NodeToUserVotesTotals::<T>::get((candidate.clone(), delegator.clone())),
+            }
+        }
+
         pub fn get_validator_supporter_share(validator: &T::AccountId) -> u8 {
             let candidate_metadata = NodeMetadata::<T>::get(validator.clone());
             if candidate_metadata.is_none() {
@@ -495,52 +1509,411 @@ pub mod pallet {
             }
         }
 
+        fn do_delegate_votes(
+            delegator: T::AccountId,
+            delegations: Vec<ValidatorDelegations<T>>,
+        ) -> DispatchResult {
+            if delegations.len() == 0 {
+                return Err(Error::<T>::EmptyDelegationList.into());
+            }
+            if (delegations.len() as u32) > T::MaxCandidates::get() {
+                return Err(Error::<T>::DelegationListTooLarge.into());
+            }
+
+            let maybe_voting_interest = UsersVotingInterests::<T>::get(delegator.clone());
+            if maybe_voting_interest.is_none() {
+                return Err(Error::<T>::DelegatorHasNoVotingCapacity.into());
+            }
+            let voting_interest = maybe_voting_interest.unwrap();
+            Self::validate_delegations(&delegator, &voting_interest, &delegations)?;
+            let _ = Self::delegate_votes_to_candidates(&delegator, delegations);
+            Self::deposit_event(Event::VotesDelegatedBy(delegator));
+            Ok(())
+        }
+
+        fn do_remove_votes(
+            voter: T::AccountId,
+            candidate: T::AccountId,
+            votes: u64,
+        ) -> DispatchResult {
+            if !NodeAccumulativeVotes::<T>::contains_key(candidate.clone()) {
+                return Err(Error::<T>::CandidateDoesNotExist.into());
+            }
+            let delegated_votes =
+                UserToNodeVotesTotals::<T>::get((voter.clone(), candidate.clone()));
+            if delegated_votes == 0 {
+                return Err(Error::<T>::VoterDidntDelegateToThisCandidate.into());
+            }
+            if votes > delegated_votes {
+                return Err(Error::<T>::AttemptingToRemoveMoreVotesThanDelegated.into());
+            }
+            Self::ensure_delegation_unlocked(&voter, &candidate)?;
+
+            Self::remove_votes_from_candidate(&voter, &candidate, votes);
+            Ok(())
+        }
+
+        fn do_redistribute_votes(
+            voter: T::AccountId,
+            from: T::AccountId,
+            to: T::AccountId,
+        ) -> DispatchResult {
+            if !Self::is_valid_candidate(&to)
+                || !NodeAccumulativeVotes::<T>::contains_key(from.clone())
+            {
+                return Err(Error::<T>::CandidateDoesNotExist.into());
+            }
+            let delegated_votes = UserToNodeVotesTotals::<T>::get((voter.clone(), from.clone()));
+            if delegated_votes == 0 {
+                return Err(Error::<T>::VoterDidntDelegateToThisCandidate.into());
+            }
+            Self::ensure_delegation_unlocked(&voter, &from)?;
+            if Self::is_delegation_locked(&voter, &to) {
+                return Err(Error::<T>::CannotAddToLockedDelegation.into());
+            }
+            Self::remove_votes_from_candidate(&voter, &from, delegated_votes);
+            Self::add_votes_to_candidate(&voter, &to, delegated_votes);
+            Ok(())
+        }
+
+        fn do_redistribute_votes_batch(
+            voter: T::AccountId,
+            moves: Vec<VoteMove<T>>,
+        ) -> DispatchResult {
+            if moves.len() == 0 {
+                return Err(Error::<T>::EmptyVoteMoveList.into());
+            }
+            if (moves.len() as u32) > T::MaxCandidates::get() {
+                return Err(Error::<T>::TooManyVoteMoves.into());
+            }
+
+            // replay the moves against a copy of the delegations before touching storage
+            let mut simulated_delegations: BTreeMap<T::AccountId, u64> = BTreeMap::new();
+            let mut total_moved: u64 = 0;
+            for vote_move in moves.iter() {
+                if vote_move.votes == 0 || vote_move.from == vote_move.to {
+                    return Err(Error::<T>::InvalidVoteMove.into());
+                }
+                if !NodeAccumulativeVotes::<T>::contains_key(vote_move.from.clone())
+                    || !Self::is_valid_candidate(&vote_move.to)
+                {
+                    return Err(Error::<T>::CandidateDoesNotExist.into());
+                }
+                let from_votes = *simulated_delegations
+                    .entry(vote_move.from.clone())
+                    .or_insert_with(|| {
+                        UserToNodeVotesTotals::<T>::get((voter.clone(), vote_move.from.clone()))
+                    });
+                if from_votes == 0 {
+                    return Err(Error::<T>::VoterDidntDelegateToThisCandidate.into());
+                }
+                if vote_move.votes > from_votes {
+                    return Err(Error::<T>::AttemptingToRemoveMoreVotesThanDelegated.into());
+                }
+                Self::ensure_delegation_unlocked(&voter, &vote_move.from)?;
+                if Self::is_delegation_locked(&voter, &vote_move.to) {
+                    return Err(Error::<T>::CannotAddToLockedDelegation.into());
+                }
+
+                simulated_delegations.insert(vote_move.from.clone(), from_votes - vote_move.votes);
+                let to_votes = simulated_delegations
+                    .entry(vote_move.to.clone())
+                    .or_insert_with(|| {
+                        UserToNodeVotesTotals::<T>::get((voter.clone(), vote_move.to.clone()))
+                    });
+                *to_votes = to_votes.saturating_add(vote_move.votes);
+                total_moved = total_moved.saturating_add(vote_move.votes);
+            }
+
+            let number_of_moves = moves.len() as u32;
+            for vote_move in moves {
+                Self::remove_votes_from_candidate(&voter, &vote_move.from, vote_move.votes);
+                Self::add_votes_to_candidate(&voter, &vote_move.to, vote_move.votes);
+            }
+            Self::deposit_event(Event::VotesRedistributed(
+                voter,
+                number_of_moves,
+                total_moved,
+            ));
+            Ok(())
+        }
+
+        /// pick the validator set out of the candidates sorted by votes
+        ///
+        /// invulnerable nodes come first unless they are jailed or have no registered session
+        /// keys. candidates that are not active or have no registered session keys are passed
+        /// over.
+        fn select_validators(sorted_candidates: Vec<T::AccountId>) -> Vec<T::AccountId> {
+            let max_validators = MaxValidatorNodes::<T>::get().min(T::MaxValidatorNodes::get());
+            let mut validators: Vec<T::AccountId> = InvulnerableNodes::<T>::get()
+                .into_inner()
+                .into_iter()
+                .filter(|node| {
+                    let jailed = matches!(
+                        CandidateStatuses::<T>::get(node.clone()),
+                        CandidateStatus::Jailed { .. }
+                    );
+                    !jailed && T::SessionKeysRegistered::contains(node)
+                })
+                .collect();
+            for candidate in sorted_candidates {
+                if validators.len() >= max_validators as usize {
+                    break;
+                }
+                if validators.contains(&candidate)
+                    || !Self::is_electable(&candidate)
+                    || !T::SessionKeysRegistered::contains(&candidate)
+                {
+                    continue;
+                }
+                validators.push(candidate);
+            }
+            validators.truncate(max_validators as usize);
+            validators
+        }
+
+        /// stash of the candidate managed by the signer
+        ///
+        /// a candidate without a controller manages itself
+        fn ensure_candidate_controller(
+            origin: OriginFor<T>,
+        ) -> Result<T::AccountId, DispatchError> {
+            let caller = ensure_signed(origin)?;
+            if let Some(stash) = ControllerToStash::<T>::get(caller.clone()) {
+                return Ok(stash);
+            }
+            if CandidateControllers::<T>::contains_key(caller.clone()) {
+                return Err(Error::<T>::NotController.into());
+            }
+            Ok(caller)
+        }
+
+        /// stash an account acts for, itself if it is not a controller
+        pub fn stash_of(account_id: &T::AccountId) -> T::AccountId {
+            ControllerToStash::<T>::get(account_id.clone()).unwrap_or(account_id.clone())
+        }
+
+        fn root_or_admin(origin: OriginFor<T>) -> Result<(), BadOrigin> {
+            let origin = ensure_signed_or_root(origin)?;
+            match origin {
+                Some(caller) => {
+                    let admin = PalletAdmin::<T>::get();
+                    if admin.is_some() && admin.unwrap() == caller {
+                        return Ok(());
+                    } else {
+                        return Err(BadOrigin);
+                    }
+                }
+                None => {
+                    return Ok(());
+                }
+            }
+        }
+
+        /// take the tokens for voting interest without going through a contract
+        fn burn_natively(token_burner: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+            match NativeBurnDestination::<T>::get() {
+                BurnDestination::Burn => {
+                    // burning is permanent, so no lock of any kind may cover the tokens
+                    let burned = <T as Config>::Currency::burn(amount);
+                    <T as Config>::Currency::settle(
+                        token_burner,
+                        burned,
+                        WithdrawReasons::all(),
+                        ExistenceRequirement::KeepAlive,
+                    )
+                    .map_err(|_| Error::<T>::InsufficientBalanceToBurn)?;
+                }
+                BurnDestination::Account(recipient) => {
+                    <T as Config>::Currency::transfer(
+                        token_burner,
+                        &recipient,
+                        amount,
+                        ExistenceRequirement::KeepAlive,
+                    )?;
+                }
+            }
+            Ok(())
+        }
+
+        /// burn or route `amount_to_burn` of `token_burner` and credit the voting interest
+        fn do_add_voting_interest(
+            token_burner: T::AccountId,
+            beneficiary_voter: T::AccountId,
+            amount_to_burn: BalanceOf<T>,
+        ) -> DispatchResultWithPostInfo {
+            if amount_to_burn < MinimumBurnAmount::<T>::get() {
+                return Err(Error::<T>::BurnAmountBelowMinimum.into());
+            }
+
+            let gas_consumed = match BurnContractHook::<T>::get() {
+                Some(burn_contract) => Self::call_burn_contract(
+                    token_burner.clone(),
+                    beneficiary_voter.clone(),
+                    amount_to_burn,
+                    burn_contract,
+                )?,
+                None => {
+                    Self::burn_natively(&token_burner, amount_to_burn)?;
+                    Weight::zero()
+                }
+            };
+
+            let voting_interest_increase =
+                Self::calculate_voting_interests(&token_burner, amount_to_burn);
+            VotingInterestSpent::<T>::mutate(token_burner.clone(), |spent| {
+                *spent = spent.saturating_add(amount_to_burn)
+            });
+            Self::add_voting_interest_internal(beneficiary_voter.clone(), voting_interest_increase);
+            Self::deposit_event(Event::VotingInterestAdded(
+                token_burner,
+                beneficiary_voter,
+                amount_to_burn,
+                voting_interest_increase,
+            ));
+            let actual_weight = T::DbWeight::get()
+                .reads_writes(6, 5)
+                .saturating_add(gas_consumed)
+                .saturating_add(Self::reward_settlement_weight(
+                    T::MaxDelegationPolicyEntries::get() as u64,
+                ));
+            Ok(Some(actual_weight).into())
+        }
+
+        /// route a purchase through the burn contract hook, returning the gas it consumed
         fn call_burn_contract(
             token_burner: T::AccountId,
             voter: T::AccountId,
-            main_pool: T::AccountId,
             amount: BalanceOf<T>,
             burn_contract: T::AccountId,
-            weight: Weight,
-        ) -> Result<(), DispatchError> {
-            let decimals: BalanceOf<T> = T::CurrencySubUnits::get();
-            let burn_minimum: BalanceOf<T> = <BalanceOf<T>>::from(100u32).saturating_mul(decimals);
-            if amount < burn_minimum {
-                return Err(Error::<T>::BurnAmountMustBeGreaterThan100.into());
-            }
-            //0xb1efc17b
-            let mut selector: Vec<u8> = [0xb1, 0xef, 0xc1, 0x7b].into();
-            let mut encoded_voter: Vec<u8> = voter.encode();
-            let mut encoded_burn_contract: Vec<u8> = burn_contract.encode();
-            let mut data_for_contract_call = Vec::new();
-            data_for_contract_call.append(&mut selector);
-            data_for_contract_call.append(&mut encoded_voter);
-            data_for_contract_call.append(&mut encoded_burn_contract);
-
-            let contract_call_result = pallet_contracts::Pallet::<T>::bare_call(
+        ) -> Result<Weight, DispatchError> {
+            let main_pool_opt = ContractRegistry::<T>::get(ContractOperation::BurnForVotes);
+            if main_pool_opt.is_none() {
+                return Err(Error::<T>::ContractNotRegistered.into());
+            }
+            let main_pool = main_pool_opt.unwrap();
+            let data_for_contract_call = main_pool.call_data((voter, burn_contract).encode());
+
+            let contract_call = pallet_contracts::Pallet::<T>::bare_call(
                 token_burner,
-                main_pool,
+                main_pool.address,
                 amount,
-                weight,
+                T::MaxBurnContractGas::get(),
                 None,
                 data_for_contract_call,
                 false,
                 pallet_contracts::Determinism::Enforced,
-            )
-            .result;
-            if let Err(e) = contract_call_result {
+            );
+            if let Err(e) = contract_call.result {
                 return Err(e);
             }
+            Ok(contract_call.gas_consumed)
+        }
+
+        /// gas a contract may use when it is dry run
+        pub fn dry_run_gas_limit() -> Weight {
+            let max_block = T::BlockWeights::get().max_block;
+            Weight::from_parts(max_block.ref_time() / 2, max_block.proof_size() / 2)
+        }
+
+        /// register `entry` for `operation` once a dry run as `caller` succeeds, or clear it
+        ///
+        /// without a caller the dry run is made as the contract itself
+        fn do_register_contract(
+            operation: ContractOperation,
+            entry: Option<ContractEntry<T::AccountId>>,
+            caller: Option<T::AccountId>,
+            call_args: Vec<u8>,
+        ) -> DispatchResult {
+            match entry {
+                Some(entry) => {
+                    let dry_run_origin = caller.unwrap_or(entry.address.clone());
+                    if !Self::dry_run_contract_call(&entry, dry_run_origin, call_args) {
+                        return Err(Error::<T>::ContractDryRunFailed.into());
+                    }
+                    ContractRegistry::<T>::insert(operation, entry.clone());
+                    Self::deposit_event(Event::ContractRegistered(operation, entry));
+                }
+                None => {
+                    ContractRegistry::<T>::remove(operation);
+                    Self::deposit_event(Event::ContractDeregistered(operation));
+                }
+            }
             Ok(())
         }
 
-        fn calculate_voting_interests(amount: BalanceOf<T>) -> u64 {
+        /// call `entry` with `call_args` and roll back everything the call did
+        ///
+        /// returns whether the contract executed the message without reverting
+        fn dry_run_contract_call(
+            entry: &ContractEntry<T::AccountId>,
+            origin: T::AccountId,
+            call_args: Vec<u8>,
+        ) -> bool {
+            let data = entry.call_data(call_args);
+            let send_value: BalanceOf<T> = Zero::zero();
+            let responded = with_transaction(|| {
+                let contract_call_result = pallet_contracts::Pallet::<T>::bare_call(
+                    origin,
+                    entry.address.clone(),
+                    send_value,
+                    Self::dry_run_gas_limit(),
+                    None,
+                    data,
+                    false,
+                    pallet_contracts::Determinism::Enforced,
+                )
+                .result;
+                let responded = match contract_call_result {
+                    Ok(return_value) => !return_value.did_revert(),
+                    Err(_) => false,
+                };
+                TransactionOutcome::Rollback(Ok::<bool, DispatchError>(responded))
+            });
+            responded.unwrap_or(false)
+        }
+
+        /// votes that spending `amount` would buy if `payer` paid for them right now
+        pub fn preview_voting_interest(payer: T::AccountId, amount: BalanceOf<T>) -> u64 {
+            Self::calculate_voting_interests(&payer, amount)
+        }
+
+        fn calculate_voting_interests(payer: &T::AccountId, amount: BalanceOf<T>) -> u64 {
+            let pricing = VotingInterestPricingCurve::<T>::get();
+            let spent_before = VotingInterestSpent::<T>::get(payer.clone());
+            let spent_after = spent_before.saturating_add(amount);
+
+            // walk the piecewise constant rate over [spent_before, spent_after)
+            let mut weighted_amount: BalanceOf<T> = Zero::zero();
+            let mut piece_start: BalanceOf<T> = Zero::zero();
+            let mut piece_rate = Perbill::one();
+            for tier in pricing.tiers.iter() {
+                let overlap = tier
+                    .threshold
+                    .min(spent_after)
+                    .saturating_sub(piece_start.max(spent_before));
+                weighted_amount = weighted_amount.saturating_add(piece_rate.mul_floor(overlap));
+                piece_start = tier.threshold;
+                piece_rate = tier.rate;
+            }
+            let overlap = spent_after.saturating_sub(piece_start.max(spent_before));
+            weighted_amount = weighted_amount.saturating_add(piece_rate.mul_floor(overlap));
+
             let sub_units = T::CurrencySubUnits::get();
-            let votes_bought = amount / sub_units;
-            votes_bought.try_into().unwrap_or(0)
+            let votes_bought: u64 = (weighted_amount / sub_units).try_into().unwrap_or(0);
+
+            let current_session = CurrentSessionIndex::<T>::get();
+            let bonus = pricing
+                .time_multipliers
+                .iter()
+                .find(|multiplier| current_session < multiplier.until_session)
+                .map(|multiplier| multiplier.bonus.mul_floor(votes_bought))
+                .unwrap_or(0);
+            votes_bought.saturating_add(bonus)
         }
 
         fn validate_delegations(
+            delegator: &T::AccountId,
             voting_interest: &VotingInterest,
             delegations: &Vec<ValidatorDelegations<T>>,
         ) -> Result<(), DispatchError> {
@@ -555,6 +1928,9 @@ pub mod pallet {
                 if !Self::is_valid_candidate(&delegation.candidate) {
                     return Err(Error::<T>::CandidateDoesNotExist.into());
                 }
+                if Self::is_delegation_locked(delegator, &delegation.candidate) {
+                    return Err(Error::<T>::CannotAddToLockedDelegation.into());
+                }
                 votes_to_distribute = votes_to_distribute.saturating_add(delegation.votes);
                 if votes_to_distribute > available_votes {
                     return Err(Error::<T>::DelegatorHasInsufficientVotes.into());
@@ -563,8 +1939,678 @@ pub mod pallet {
             Ok(())
         }
 
+        fn validate_delegation_policy(
+            policy: &BoundedVec<DelegationPolicyEntry<T>, T::MaxDelegationPolicyEntries>,
+        ) -> Result<(), DispatchError> {
+            let mut total_share: u32 = 0;
+            for (index, entry) in policy.iter().enumerate() {
+                if !Self::is_valid_candidate(&entry.candidate) {
+                    return Err(Error::<T>::CandidateDoesNotExist.into());
+                }
+                if policy
+                    .iter()
+                    .skip(index + 1)
+                    .any(|other| other.candidate == entry.candidate)
+                {
+                    return Err(Error::<T>::DuplicateCandidateInDelegationPolicy.into());
+                }
+                total_share = total_share.saturating_add(entry.share.deconstruct() as u32);
+            }
+            if total_share != 100 {
+                return Err(Error::<T>::DelegationPolicySharesMustTotal100.into());
+            }
+            Ok(())
+        }
+
+        /// delegate `votes` of `delegator` according to their delegation policy
+        ///
+        /// shares are treated as relative weights so a policy that lost a candidate keeps
+        /// splitting between the remaining ones. rounding dust goes to the first entry.
+        /// votes sent to a candidate the delegator has a running lock with join that lock.
+        fn apply_delegation_policy(delegator: &T::AccountId, votes: u64) {
+            if votes == 0 {
+                return;
+            }
+            let policy_opt = DelegationPolicies::<T>::get(delegator.clone());
+            if policy_opt.is_none() {
+                return;
+            }
+            let entries: Vec<DelegationPolicyEntry<T>> = policy_opt
+                .unwrap()
+                .into_iter()
+                .filter(|entry| Self::is_valid_candidate(&entry.candidate))
+                .collect();
+            let total_share: u64 = entries
+                .iter()
+                .map(|entry| entry.share.deconstruct() as u64)
+                .sum();
+            if total_share == 0 {
+                return;
+            }
+
+            let mut allocations: Vec<(T::AccountId, u64)> = Vec::new();
+            let mut allocated: u64 = 0;
+            for entry in entries.iter() {
+                let portion =
+                    Perquintill::from_rational(entry.share.deconstruct() as u64, total_share)
+                        .mul_floor(votes);
+                allocated = allocated.saturating_add(portion);
+                allocations.push((entry.candidate.clone(), portion));
+            }
+            if let Some(first) = allocations.first_mut() {
+                first.1 = first.1.saturating_add(votes.saturating_sub(allocated));
+            }
+            for (candidate, portion) in allocations {
+                if portion > 0 {
+                    Self::add_votes_to_candidate(delegator, &candidate, portion);
+                }
+            }
+        }
+
+        /// drop `removed_candidate` from the supporter's policy and re-delegate the votes
+        /// that were freed by its removal
+        fn rebalance_freed_votes(
+            supporter: &T::AccountId,
+            removed_candidate: &T::AccountId,
+            freed_votes: u64,
+        ) {
+            let has_policy =
+                DelegationPolicies::<T>::mutate_exists(supporter.clone(), |policy_opt| {
+                    if let Some(policy) = policy_opt.as_mut() {
+                        policy.retain(|entry| &entry.candidate != removed_candidate);
+                    }
+                    if policy_opt
+                        .as_ref()
+                        .map_or(false, |policy| policy.is_empty())
+                    {
+                        *policy_opt = None;
+                    }
+                    policy_opt.is_some()
+                });
+            if !has_policy {
+                return;
+            }
+            Self::apply_delegation_policy(supporter, freed_votes);
+            Self::deposit_event(Event::FreedVotesRebalanced(
+                supporter.clone(),
+                removed_candidate.clone(),
+                freed_votes,
+            ));
+        }
+
+        /// remove a candidate together with all the support it received
+        fn remove_candidate(candidate: &T::AccountId) {
+            let support_to_remove: Vec<(T::AccountId, u64)> =
+                NodeToUserVotesTotals::<T>::iter_prefix((candidate.clone(),)).collect();
+            for (supporter, delegated_votes) in support_to_remove.iter() {
+                Self::remove_votes_from_candidate(supporter, candidate, *delegated_votes);
+            }
+            NodeAccumulativeVotes::<T>::remove(candidate.clone());
+            BootstrapVotes::<T>::remove(candidate.clone());
+            NodeMetadata::<T>::remove(candidate.clone());
+            if let Some(controller) = CandidateControllers::<T>::take(candidate.clone()) {
+                ControllerToStash::<T>::remove(controller);
+            }
+            CandidateStatuses::<T>::remove(candidate.clone());
+            LastJailedSession::<T>::remove(candidate.clone());
+            let deposit = NodeMetadataDeposits::<T>::take(candidate.clone());
+            <T as Config>::Currency::unreserve(candidate, deposit);
+            PendingSharePercentChanges::<T>::remove(candidate.clone());
+            CurrentNumberOfCandidatesNodes::<T>::mutate(|count| *count = count.saturating_sub(1));
+            T::NodeRewardManager::on_candidate_removed(candidate);
+            // the candidate is no longer valid, so policies can only re-delegate elsewhere
+            for (supporter, delegated_votes) in support_to_remove {
+                Self::rebalance_freed_votes(&supporter, candidate, delegated_votes);
+            }
+        }
+
+        fn archive_session(
+            session_index: SessionIndex,
+            validator_stats: Vec<ValidatorVoteStats<T>>,
+        ) {
+            let archived_validators: Vec<ArchivedValidator<T::AccountId>> = validator_stats
+                .into_iter()
+                .map(|stats| ArchivedValidator {
+                    sharing_percent: Self::get_validator_supporter_share(&stats.account_id),
+                    account_id: stats.account_id,
+                    total_votes: stats.total_votes,
+                    self_votes: stats.self_votes,
+                    delegated_votes: stats.delegated_votes,
+                    performance: stats.performance,
+                })
+                .collect();
+            SessionArchive::<T>::insert(
+                session_index,
+                BoundedVec::truncate_from(archived_validators),
+            );
+            Self::prune_history(session_index);
+        }
+
+        /// remove archived sessions and node lists older than `HistoryDepth` sessions
+        ///
+        /// a depth of 0 is treated as 1 so the session that was just archived is kept
+        fn prune_history(current_index: SessionIndex) {
+            let first_retained = current_index
+                .saturating_add(1)
+                .saturating_sub(T::HistoryDepth::get().max(1));
+            let mut oldest = OldestRetainedSession::<T>::get();
+            let mut pruned: u32 = 0;
+            while oldest < first_retained && pruned < MAX_SESSIONS_PRUNED_PER_CALL {
+                SessionArchive::<T>::remove(oldest);
+                SessionNodeList::<T>::remove(oldest);
+                oldest = oldest.saturating_add(1);
+                pruned = pruned.saturating_add(1);
+            }
+            OldestRetainedSession::<T>::put(oldest);
+        }
+
+        /// reserve or release funds so the candidate's deposit matches `metadata`
+        fn update_metadata_deposit(
+            candidate: &T::AccountId,
+            metadata: &NodeMetadataStruct,
+        ) -> DispatchResult {
+            let byte_deposit = T::MetadataDepositPerByte::get()
+                .saturating_mul(BalanceOf::<T>::from(metadata.encoded_size() as u32));
+            let required = T::MetadataDepositBase::get().saturating_add(byte_deposit);
+            let current = NodeMetadataDeposits::<T>::get(candidate.clone());
+            if required == current {
+                return Ok(());
+            }
+            if required > current {
+                <T as Config>::Currency::reserve(candidate, required.saturating_sub(current))
+                    .map_err(|_| Error::<T>::InsufficientBalanceForMetadataDeposit)?;
+            } else {
+                <T as Config>::Currency::unreserve(candidate, current.saturating_sub(required));
+            }
+            NodeMetadataDeposits::<T>::insert(candidate.clone(), required);
+            Self::deposit_event(Event::MetadataDepositUpdated(candidate.clone(), required));
+            Ok(())
+        }
+
+        /// apply the sharing percentage changes whose notice period ends by `session_index`
+        fn apply_due_share_changes(session_index: SessionIndex) {
+            let due_changes: Vec<(T::AccountId, PendingSharePercentChange)> =
+                PendingSharePercentChanges::<T>::iter()
+                    .filter(|(_, change)| change.effective_session <= session_index)
+                    .collect();
+            for (node_id, change) in due_changes {
+                PendingSharePercentChanges::<T>::remove(node_id.clone());
+                let applied = NodeMetadata::<T>::mutate(node_id.clone(), |metadata_opt| {
+                    if let Some(metadata) = metadata_opt {
+                        metadata.sharing_percent = change.sharing_percent;
+                        metadata.index_of_last_percent_change = session_index;
+                        metadata.record_commission_change(session_index, change.sharing_percent);
+                        return true;
+                    }
+                    false
+                });
+                if applied {
+                    Self::deposit_event(Event::SupporterShareChanged(
+                        node_id,
+                        change.sharing_percent,
+                    ));
+                }
+            }
+        }
+
+        /// effective weight of `raw_votes` under a delegation's state in `session_index`
+        fn effective_votes(
+            raw_votes: u64,
+            weight: &DelegationWeight,
+            decay: &VoteDecayParameters,
+            session_index: SessionIndex,
+        ) -> u64 {
+            if session_index < weight.locked_until {
+                return raw_votes.saturating_mul(weight.conviction.multiplier());
+            }
+            // decay starts once the delegation was last refreshed or its lock ran out
+            let idle_sessions =
+                session_index.saturating_sub(weight.refreshed_at.max(weight.locked_until));
+            let retained = Perbill::one()
+                .saturating_sub(decay.decay_per_session)
+                .saturating_pow(idle_sessions as usize)
+                .max(decay.floor);
+            retained.mul_floor(raw_votes)
+        }
+
+        /// update the state of a delegation and move the change of its effective weight into
+        /// `NodeAccumulativeVotes`
+        ///
+        /// `previous_raw_votes` is the raw vote count before the caller changed it, which is
+        /// the weight of delegations that have no state yet.
+        fn sync_delegation_weight(
+            delegator: &T::AccountId,
+            candidate: &T::AccountId,
+            previous_raw_votes: u64,
+            update: impl FnOnce(&mut DelegationWeight),
+        ) {
+            let current_index = CurrentSessionIndex::<T>::get();
+            let key = (candidate.clone(), delegator.clone());
+            let raw_votes = NodeToUserVotesTotals::<T>::get(key.clone());
+            let existing_weight = DelegationWeights::<T>::get(key.clone());
+            let previous_effective_votes = existing_weight
+                .as_ref()
+                .map(|weight| weight.effective_votes)
+                .unwrap_or(previous_raw_votes);
+            T::NodeRewardManager::on_delegation_weight_change(
+                delegator,
+                candidate,
+                previous_effective_votes,
+            );
+
+            let mut effective_votes = 0;
+            if raw_votes == 0 {
+                DelegationWeights::<T>::remove(key);
+                T::NodeRewardManager::on_delegation_removed(delegator, candidate);
+            } else {
+                let decay = VoteDecay::<T>::get();
+                let mut weight = existing_weight.unwrap_or(DelegationWeight {
+                    effective_votes: previous_raw_votes,
+                    conviction: Conviction::None,
+                    locked_until: 0,
+                    refreshed_at: current_index,
+                });
+                update(&mut weight);
+                weight.effective_votes =
+                    Self::effective_votes(raw_votes, &weight, &decay, current_index);
+                effective_votes = weight.effective_votes;
+                if Self::needs_delegation_weight(&weight, raw_votes, &decay, current_index) {
+                    DelegationWeights::<T>::insert(key, weight);
+                } else {
+                    DelegationWeights::<T>::remove(key);
+                }
+            }
+            Self::apply_weight_change(candidate, previous_effective_votes, effective_votes);
+        }
+
+        fn apply_weight_change(candidate: &T::AccountId, previous_votes: u64, new_votes: u64) {
+            NodeAccumulativeVotes::<T>::mutate(candidate.clone(), |candidate_votes_opt| {
+                if let Some(candidate_votes) = candidate_votes_opt {
+                    *candidate_votes = candidate_votes
+                        .saturating_sub(previous_votes)
+                        .saturating_add(new_votes);
+                }
+            });
+        }
+
+        /// whether the delegation of `delegator` to `candidate` is in a running lock
+        ///
+        /// votes cannot be added to such a delegation explicitly, as they would silently join
+        /// a lock that was taken for a smaller amount
+        fn is_delegation_locked(delegator: &T::AccountId, candidate: &T::AccountId) -> bool {
+            DelegationWeights::<T>::get((candidate.clone(), delegator.clone()))
+                .map(|weight| CurrentSessionIndex::<T>::get() < weight.locked_until)
+                .unwrap_or(false)
+        }
+
+        /// votes in a running lock stay put, unless the candidate has scheduled a cut of the
+        /// supporter share or is retiring, in which case supporters may leave
+        fn ensure_delegation_unlocked(
+            delegator: &T::AccountId,
+            candidate: &T::AccountId,
+        ) -> DispatchResult {
+            if !Self::is_delegation_locked(delegator, candidate) {
+                return Ok(());
+            }
+            let share_cut_pending = PendingSharePercentChanges::<T>::get(candidate.clone())
+                .map(|change| {
+                    change.sharing_percent < Self::get_validator_supporter_share(candidate)
+                })
+                .unwrap_or(false);
+            if share_cut_pending || !Self::is_valid_candidate(candidate) {
+                return Ok(());
+            }
+            Err(Error::<T>::DelegationLocked.into())
+        }
+
+        /// bring effective weights up to date with decay and expired locks
+        ///
+        /// every delegation is visited once per session. the sweep is spread over as many
+        /// blocks as needed, resuming from `DecaySweepCursor`.
+        fn sweep_delegation_weights(remaining_weight: Weight) -> Weight {
+            let per_delegation = T::DbWeight::get().reads_writes(3, 2);
+            let mut consumed = T::DbWeight::get().reads(2);
+            if remaining_weight.any_lt(consumed) {
+                return Weight::zero();
+            }
+            let current_index = CurrentSessionIndex::<T>::get();
+            if LastDecaySweepSession::<T>::get() >= current_index {
+                return consumed;
+            }
+
+            let decay = VoteDecay::<T>::get();
+            consumed = consumed.saturating_add(T::DbWeight::get().reads(2));
+            let mut delegations = match DecaySweepCursor::<T>::get() {
+                Some(cursor) => NodeToUserVotesTotals::<T>::iter_from(cursor.into_inner()),
+                None => NodeToUserVotesTotals::<T>::iter(),
+            };
+            loop {
+                let needed = consumed
+                    .saturating_add(per_delegation)
+                    .saturating_add(T::DbWeight::get().writes(2));
+                if remaining_weight.any_lt(needed) {
+                    match BoundedVec::try_from(delegations.last_raw_key().to_vec()) {
+                        Ok(cursor) => DecaySweepCursor::<T>::put(cursor),
+                        Err(_) => DecaySweepCursor::<T>::kill(),
+                    }
+                    return consumed.saturating_add(T::DbWeight::get().writes(1));
+                }
+                match delegations.next() {
+                    Some(((candidate, delegator), raw_votes)) => {
+                        consumed = consumed.saturating_add(per_delegation);
+                        Self::decay_delegation(
+                            &candidate,
+                            &delegator,
+                            raw_votes,
+                            &decay,
+                            current_index,
+                        );
+                    }
+                    None => {
+                        DecaySweepCursor::<T>::kill();
+                        LastDecaySweepSession::<T>::put(current_index);
+                        return consumed.saturating_add(T::DbWeight::get().writes(2));
+                    }
+                }
+            }
+        }
+
+        fn decay_delegation(
+            candidate: &T::AccountId,
+            delegator: &T::AccountId,
+            raw_votes: u64,
+            decay: &VoteDecayParameters,
+            session_index: SessionIndex,
+        ) {
+            let key = (candidate.clone(), delegator.clone());
+            let existing_weight = DelegationWeights::<T>::get(key.clone());
+            let mut weight = existing_weight.clone().unwrap_or(DelegationWeight {
+                effective_votes: raw_votes,
+                conviction: Conviction::None,
+                locked_until: 0,
+                refreshed_at: session_index,
+            });
+            let previous_effective_votes = weight.effective_votes;
+            weight.effective_votes =
+                Self::effective_votes(raw_votes, &weight, decay, session_index);
+            let needs_weight =
+                Self::needs_delegation_weight(&weight, raw_votes, decay, session_index);
+            let unchanged = match existing_weight.as_ref() {
+                Some(existing_weight) => needs_weight && existing_weight == &weight,
+                None => !needs_weight,
+            };
+            if unchanged {
+                return;
+            }
+            T::NodeRewardManager::on_delegation_weight_change(
+                delegator,
+                candidate,
+                previous_effective_votes,
+            );
+            let effective_votes = weight.effective_votes;
+            if needs_weight {
+                DelegationWeights::<T>::insert(key, weight);
+            } else {
+                DelegationWeights::<T>::remove(key);
+            }
+            Self::apply_weight_change(candidate, previous_effective_votes, effective_votes);
+        }
+
+        /// whether a delegation's state has to be stored, one without an entry in
+        /// `DelegationWeights` weighs its raw vote count
+        ///
+        /// that is the case while it is locked, while votes decay or if its weight differs
+        fn needs_delegation_weight(
+            weight: &DelegationWeight,
+            raw_votes: u64,
+            decay: &VoteDecayParameters,
+            session_index: SessionIndex,
+        ) -> bool {
+            session_index < weight.locked_until
+                || !decay.decay_per_session.is_zero()
+                || weight.effective_votes != raw_votes
+        }
+
+        /// candidate that can receive votes, i.e. one that is not retiring
         fn is_valid_candidate(candidate: &T::AccountId) -> bool {
+            if !NodeAccumulativeVotes::<T>::contains_key(candidate.clone()) {
+                return false;
+            }
+            match CandidateStatuses::<T>::get(candidate.clone()) {
+                CandidateStatus::Retiring { .. } => false,
+                _ => true,
+            }
+        }
+
+        pub fn is_electable(candidate: &T::AccountId) -> bool {
             NodeAccumulativeVotes::<T>::contains_key(candidate.clone())
+                && CandidateStatuses::<T>::get(candidate.clone()) == CandidateStatus::Active
+        }
+
+        /// jail `candidate` until `sessions` sessions from now, extending an earlier jailing
+        fn jail(candidate: &T::AccountId, sessions: SessionIndex) {
+            let current_index = CurrentSessionIndex::<T>::get();
+            let until = current_index.saturating_add(sessions);
+            let chilled = match CandidateStatuses::<T>::get(candidate.clone()) {
+                // a retiring candidate is never elected again, the offence is still recorded
+                CandidateStatus::Retiring { .. } => {
+                    LastJailedSession::<T>::insert(candidate.clone(), current_index);
+                    return;
+                }
+                CandidateStatus::Jailed {
+                    until: jailed_until,
+                    ..
+                } if jailed_until >= until => return,
+                CandidateStatus::Jailed { chilled, .. } => chilled,
+                CandidateStatus::Chilled => true,
+                CandidateStatus::Active => false,
+            };
+            CandidateStatuses::<T>::insert(
+                candidate.clone(),
+                CandidateStatus::Jailed { until, chilled },
+            );
+            LastJailedSession::<T>::insert(candidate.clone(), current_index);
+            Self::deposit_event(Event::CandidateJailed(candidate.clone(), until));
+        }
+
+        /// rebuild the mirror map, candidate totals, delegated votes and candidate count from
+        /// the per-pair user -> candidate delegations
+        ///
+        /// returns the number of candidates and delegations kept
+        fn rebuild_vote_totals() -> (u32, u32) {
+            let current_index = CurrentSessionIndex::<T>::get();
+            let decay = VoteDecay::<T>::get();
+            let mut candidate_totals: BTreeMap<T::AccountId, u64> =
+                NodeAccumulativeVotes::<T>::iter_keys()
+                    .map(|candidate| {
+                        let bootstrap_votes = BootstrapVotes::<T>::get(candidate.clone());
+                        (candidate, bootstrap_votes)
+                    })
+                    .collect();
+            let mut delegated_per_user: BTreeMap<T::AccountId, u64> = BTreeMap::new();
+
+            let delegations: Vec<((T::AccountId, T::AccountId), u64)> =
+                UserToNodeVotesTotals::<T>::iter().collect();
+            // supporters are settled at the weights the reward manager has seen so far
+            for ((delegator, candidate), _) in delegations.iter() {
+                T::NodeRewardManager::on_delegation_weight_change(
+                    delegator,
+                    candidate,
+                    Self::delegation_weight(delegator, candidate),
+                );
+            }
+            let _ = NodeToUserVotesTotals::<T>::clear(u32::MAX, None);
+            let mut kept_delegations: u32 = 0;
+            for ((delegator, candidate), votes) in delegations {
+                let key = (candidate.clone(), delegator.clone());
+                let candidate_total = match candidate_totals.get_mut(&candidate) {
+                    Some(candidate_total) if votes > 0 => candidate_total,
+                    _ => {
+                        T::NodeRewardManager::on_delegation_removed(&delegator, &candidate);
+                        UserToNodeVotesTotals::<T>::remove((delegator, candidate));
+                        DelegationWeights::<T>::remove(key);
+                        continue;
+                    }
+                };
+                NodeToUserVotesTotals::<T>::insert(key.clone(), votes);
+                let effective_votes = match DelegationWeights::<T>::get(key.clone()) {
+                    Some(mut weight) => {
+                        weight.effective_votes =
+                            Self::effective_votes(votes, &weight, &decay, current_index);
+                        let effective_votes = weight.effective_votes;
+                        DelegationWeights::<T>::insert(key, weight);
+                        effective_votes
+                    }
+                    None => votes,
+                };
+                *candidate_total = candidate_total.saturating_add(effective_votes);
+                let user_delegated = delegated_per_user.entry(delegator).or_insert(0);
+                *user_delegated = user_delegated.saturating_add(votes);
+                kept_delegations = kept_delegations.saturating_add(1);
+            }
+
+            let stale_weights: Vec<(T::AccountId, T::AccountId)> =
+                DelegationWeights::<T>::iter_keys()
+                    .filter(|key| !NodeToUserVotesTotals::<T>::contains_key(key.clone()))
+                    .collect();
+            for key in stale_weights {
+                DelegationWeights::<T>::remove(key);
+            }
+
+            let number_of_candidates = candidate_totals.len() as u32;
+            for (candidate, total) in candidate_totals {
+                NodeAccumulativeVotes::<T>::insert(candidate, total);
+            }
+            CurrentNumberOfCandidatesNodes::<T>::put(number_of_candidates);
+
+            let voters: Vec<(T::AccountId, VotingInterest)> =
+                UsersVotingInterests::<T>::iter().collect();
+            for (voter, mut voting_interest) in voters {
+                voting_interest.delegated = delegated_per_user.remove(&voter).unwrap_or(0);
+                voting_interest.total = voting_interest.total.max(voting_interest.delegated);
+                UsersVotingInterests::<T>::insert(voter, voting_interest);
+            }
+            for (voter, delegated) in delegated_per_user {
+                UsersVotingInterests::<T>::insert(
+                    voter,
+                    VotingInterest {
+                        total: delegated,
+                        delegated,
+                    },
+                );
+            }
+            (number_of_candidates, kept_delegations)
+        }
+
+        /// check the invariants between the vote maps and their derived totals
+        #[cfg(any(feature = "try-runtime", test))]
+        pub fn do_try_state() -> Result<(), &'static str> {
+            let mut delegated_per_user: BTreeMap<T::AccountId, u64> = BTreeMap::new();
+            let mut user_to_node_entries: u32 = 0;
+            for ((delegator, candidate), votes) in UserToNodeVotesTotals::<T>::iter() {
+                ensure!(votes > 0, "empty delegation kept in UserToNodeVotesTotals");
+                ensure!(
+                    NodeToUserVotesTotals::<T>::get((candidate.clone(), delegator.clone()))
+                        == votes,
+                    "UserToNodeVotesTotals does not mirror NodeToUserVotesTotals"
+                );
+                ensure!(
+                    NodeAccumulativeVotes::<T>::contains_key(candidate),
+                    "votes delegated to a candidate that does not exist"
+                );
+                let user_delegated = delegated_per_user.entry(delegator).or_insert(0);
+                *user_delegated = user_delegated.saturating_add(votes);
+                user_to_node_entries += 1;
+            }
+            ensure!(
+                NodeToUserVotesTotals::<T>::iter().count() as u32 == user_to_node_entries,
+                "NodeToUserVotesTotals has entries missing from UserToNodeVotesTotals"
+            );
+
+            for (voter, voting_interest) in UsersVotingInterests::<T>::iter() {
+                let delegated = delegated_per_user.remove(&voter).unwrap_or(0);
+                ensure!(
+                    voting_interest.delegated == delegated,
+                    "delegated voting interest does not match the user's delegations"
+                );
+                ensure!(
+                    voting_interest.delegated <= voting_interest.total,
+                    "user delegated more votes than it has"
+                );
+            }
+            ensure!(
+                delegated_per_user.is_empty(),
+                "delegations from a user without voting interest"
+            );
+
+            let mut number_of_candidates: u32 = 0;
+            for (candidate, total_votes) in NodeAccumulativeVotes::<T>::iter() {
+                let effective_votes = NodeToUserVotesTotals::<T>::iter_prefix((candidate.clone(),))
+                    .fold(
+                        BootstrapVotes::<T>::get(candidate.clone()),
+                        |total, (delegator, _)| {
+                            total.saturating_add(Self::delegation_weight(&delegator, &candidate))
+                        },
+                    );
+                ensure!(
+                    total_votes == effective_votes,
+                    "candidate votes do not match the sum of its delegations"
+                );
+                number_of_candidates += 1;
+            }
+            ensure!(
+                CurrentNumberOfCandidatesNodes::<T>::get() == number_of_candidates,
+                "candidate count does not match the number of candidates"
+            );
+
+            for (candidate, delegator) in DelegationWeights::<T>::iter_keys() {
+                ensure!(
+                    NodeToUserVotesTotals::<T>::contains_key((candidate, delegator)),
+                    "delegation weight kept for a removed delegation"
+                );
+            }
+            for (stash, controller) in CandidateControllers::<T>::iter() {
+                ensure!(
+                    ControllerToStash::<T>::get(controller) == Some(stash),
+                    "CandidateControllers does not mirror ControllerToStash"
+                );
+            }
+            ensure!(
+                CandidateControllers::<T>::iter().count() == ControllerToStash::<T>::iter().count(),
+                "ControllerToStash has entries missing from CandidateControllers"
+            );
+            Ok(())
+        }
+
+        /// release candidates whose jail time is over and remove those done retiring
+        fn process_candidate_statuses(session_index: SessionIndex) {
+            let statuses: Vec<(T::AccountId, CandidateStatus)> =
+                CandidateStatuses::<T>::iter().collect();
+            for (candidate, status) in statuses {
+                match status {
+                    CandidateStatus::Jailed { until, chilled } if until <= session_index => {
+                        if chilled {
+                            CandidateStatuses::<T>::insert(
+                                candidate.clone(),
+                                CandidateStatus::Chilled,
+                            );
+                        } else {
+                            CandidateStatuses::<T>::remove(candidate.clone());
+                        }
+                        Self::deposit_event(Event::CandidateUnjailed(candidate));
+                    }
+                    // a jail can end during the session through retirement, so the session is
+                    // recorded while the candidate is still jailed
+                    CandidateStatus::Jailed { .. } => {
+                        LastJailedSession::<T>::insert(candidate.clone(), session_index);
+                    }
+                    CandidateStatus::Retiring { effective } if effective <= session_index => {
+                        Self::remove_candidate(&candidate);
+                        Self::deposit_event(Event::CandidacyRemoved(candidate));
+                    }
+                    _ => {}
+                }
+            }
         }
 
         fn delegate_votes_to_candidates(
@@ -580,13 +2626,8 @@ pub mod pallet {
         }
 
         fn add_votes_to_candidate(delegator: &T::AccountId, candidate: &T::AccountId, votes: u64) {
-            let added_votes =
-                NodeAccumulativeVotes::<T>::mutate(candidate.clone(), |candidate_votes_opt| {
-                    let candidate_votes = candidate_votes_opt.unwrap();
-                    candidate_votes.saturating_add(votes)
-                });
-            NodeAccumulativeVotes::<T>::insert(candidate.clone(), added_votes);
-
+            let previous_votes =
+                NodeToUserVotesTotals::<T>::get((candidate.clone(), delegator.clone()));
             let candidate_support = NodeToUserVotesTotals::<T>::mutate(
                 (candidate.clone(), delegator.clone()),
                 |candidate_supporters| candidate_supporters.saturating_add(votes),
@@ -605,13 +2646,16 @@ pub mod pallet {
                 vote_delegation,
             );
 
-            let user_voting_interests =
-                UsersVotingInterests::<T>::mutate(delegator.clone(), |voting_interest| {
-                    let mut voting_interest = voting_interest.clone().unwrap();
-                    voting_interest.delegated = voting_interest.delegated.saturating_add(votes);
-                    voting_interest
-                });
-            UsersVotingInterests::<T>::insert(delegator.clone(), user_voting_interests);
+            UsersVotingInterests::<T>::mutate(delegator.clone(), |voting_interest_opt| {
+                let voting_interest = voting_interest_opt.get_or_insert_with(Default::default);
+                voting_interest.delegated = voting_interest.delegated.saturating_add(votes);
+            });
+
+            // delegating more counts as activity, so the decay starts over
+            let current_index = CurrentSessionIndex::<T>::get();
+            Self::sync_delegation_weight(delegator, candidate, previous_votes, |weight| {
+                weight.refreshed_at = current_index;
+            });
         }
 
         fn remove_votes_from_candidate(
@@ -619,13 +2663,6 @@ pub mod pallet {
             candidate: &T::AccountId,
             votes: u64,
         ) {
-            let candidate_votes =
-                NodeAccumulativeVotes::<T>::mutate(candidate.clone(), |candidate_votes_opt| {
-                    let candidate_votes = candidate_votes_opt.unwrap();
-                    candidate_votes.saturating_sub(votes)
-                });
-            NodeAccumulativeVotes::<T>::insert(candidate.clone(), candidate_votes);
-
             let delegated_votes =
                 NodeToUserVotesTotals::<T>::get((candidate.clone(), delegator.clone()));
             if delegated_votes == votes {
@@ -650,39 +2687,128 @@ pub mod pallet {
                 );
             }
 
-            let user_voting_interests =
-                UsersVotingInterests::<T>::mutate(delegator.clone(), |voting_interest| {
-                    let mut voting_interest = voting_interest.clone().unwrap();
+            UsersVotingInterests::<T>::mutate(delegator.clone(), |voting_interest_opt| {
+                if let Some(voting_interest) = voting_interest_opt {
                     voting_interest.delegated = voting_interest.delegated.saturating_sub(votes);
-                    voting_interest
-                });
-            UsersVotingInterests::<T>::insert(delegator.clone(), user_voting_interests);
+                }
+            });
+
+            Self::sync_delegation_weight(delegator, candidate, delegated_votes, |_| {});
+        }
+    }
+
+    impl<T: Config, FullIdentification>
+        OnOffenceHandler<T::AccountId, (T::AccountId, FullIdentification), Weight> for Pallet<T>
+    {
+        fn on_offence(
+            offenders: &[OffenceDetails<T::AccountId, (T::AccountId, FullIdentification)>],
+            _slash_fraction: &[Perbill],
+            _session: SessionIndex,
+            _disable_strategy: DisableStrategy,
+        ) -> Weight {
+            let mut consumed = Weight::zero();
+            for details in offenders {
+                let (offender, _) = &details.offender;
+                consumed = consumed.saturating_add(T::DbWeight::get().reads_writes(3, 1));
+                if !NodeAccumulativeVotes::<T>::contains_key(offender.clone()) {
+                    continue;
+                }
+                Self::jail(offender, T::JailDuration::get());
+            }
+            consumed
+        }
+    }
+
+    impl<T: Config> NodeVotingProvider<T::AccountId> for Pallet<T> {
+        fn current_session() -> SessionIndex {
+            CurrentSessionIndex::<T>::get()
+        }
+
+        fn max_candidates() -> u32 {
+            T::MaxCandidates::get()
+        }
+
+        fn node_votes(node: &T::AccountId) -> u64 {
+            NodeAccumulativeVotes::<T>::get(node.clone()).unwrap_or(0)
+        }
+
+        fn delegated_votes(node: &T::AccountId) -> u64 {
+            NodeAccumulativeVotes::<T>::get(node.clone())
+                .unwrap_or(0)
+                .saturating_sub(BootstrapVotes::<T>::get(node.clone()))
+        }
+
+        fn delegation_weight(delegator: &T::AccountId, candidate: &T::AccountId) -> u64 {
+            Self::delegation_weight(delegator, candidate)
+        }
+
+        fn delegations_of(delegator: &T::AccountId) -> Vec<T::AccountId> {
+            Self::get_user_supported_nodes(delegator.clone())
+        }
+
+        fn supporter_share(node: &T::AccountId) -> u8 {
+            Self::get_validator_supporter_share(node)
+        }
+
+        fn was_jailed_in(node: &T::AccountId, session: SessionIndex) -> bool {
+            LastJailedSession::<T>::get(node.clone()) == Some(session)
+        }
+
+        fn contract_entry(operation: ContractOperation) -> Option<ContractEntry<T::AccountId>> {
+            ContractRegistry::<T>::get(operation)
+        }
+
+        fn set_contract_entry(
+            operation: ContractOperation,
+            entry: Option<ContractEntry<T::AccountId>>,
+            caller: Option<T::AccountId>,
+            call_args: Vec<u8>,
+        ) -> DispatchResult {
+            Self::do_register_contract(operation, entry, caller, call_args)
+        }
+
+        fn set_contract_entry_weight() -> Weight {
+            T::DbWeight::get()
+                .reads_writes(1, 1)
+                .saturating_add(Self::dry_run_gas_limit())
         }
     }
 
     impl<T: Config> SessionManager<T::AccountId> for Pallet<T> {
         fn new_session(new_index: SessionIndex) -> Option<Vec<T::AccountId>> {
-            let sorted_candidates_opt = Self::get_sorted_candidates();
-            if sorted_candidates_opt.is_none() {
-                return None;
+            let sorted_candidates = Self::get_sorted_candidates().unwrap_or_default();
+            if !sorted_candidates.is_empty() {
+                let bounded_candidates: BoundedVec<T::AccountId, ConstU32<300>> =
+                    BoundedVec::truncate_from(sorted_candidates.clone());
+                SessionNodeList::<T>::insert(new_index, bounded_candidates);
             }
-            let mut sorted_candidates = sorted_candidates_opt.unwrap();
 
-            let bounded_candidates: BoundedVec<T::AccountId, ConstU32<300>> =
-                BoundedVec::try_from(sorted_candidates.clone()).unwrap();
-            SessionNodeList::<T>::insert(new_index, bounded_candidates);
-            sorted_candidates.truncate(T::MaxValidatorNodes::get() as usize);
-
-            Some(sorted_candidates)
+            let validators = Self::select_validators(sorted_candidates);
+            let min_validators = MinValidatorNodes::<T>::get().max(1);
+            if (validators.len() as u32) < min_validators {
+                EmergencyMode::<T>::put(true);
+                Self::deposit_event(Event::ValidatorSetFrozen(
+                    new_index,
+                    validators.len() as u32,
+                ));
+                return None;
+            }
+            if EmergencyMode::<T>::take() {
+                Self::deposit_event(Event::EmergencyModeExited(new_index));
+            }
+            SelectedValidators::<T>::put(BoundedVec::truncate_from(validators.clone()));
+            Some(validators)
         }
 
         fn start_session(start_index: SessionIndex) {
             let _ = CurrentSessionIndex::<T>::put(start_index);
+            Self::apply_due_share_changes(start_index);
+            Self::process_candidate_statuses(start_index);
             let sorted_candidates_opt = Self::get_sorted_candidates();
             if sorted_candidates_opt.is_none() {
                 return;
             }
-            let mut sorted_candidates = sorted_candidates_opt.unwrap();
+            let sorted_candidates = sorted_candidates_opt.unwrap();
 
             // if at max candidates, remove the bottom 12
             if CurrentNumberOfCandidatesNodes::<T>::get() == T::MaxCandidates::get() {
@@ -690,33 +2816,25 @@ pub mod pallet {
                     let to_be_dropped: Vec<T::AccountId> =
                         sorted_candidates.clone().drain(288..).collect();
                     for candidate in to_be_dropped {
-                        NodeMetadata::<T>::remove(candidate.clone());
-                        let mut support_to_remove: Vec<(T::AccountId, u64)> = Vec::new();
-                        let mut prefix_iterator =
-                            NodeToUserVotesTotals::<T>::iter_prefix((candidate.clone(),));
-                        while let Some((supporter, delegated_votes)) = prefix_iterator.next() {
-                            support_to_remove.push((supporter, delegated_votes));
-                        }
-                        for support in support_to_remove {
-                            Self::remove_votes_from_candidate(&support.0, &candidate, support.1);
-                        }
+                        Self::remove_candidate(&candidate);
+                        Self::deposit_event(Event::CandidacyRemoved(candidate));
                     }
                 }
             }
 
-            // store validator stats
-            sorted_candidates.truncate(T::MaxValidatorNodes::get() as usize);
+            // store validator stats for the set pallet-session is starting. until this pallet
+            // has selected a set there is no reliable one, and the top of the candidate list
+            // is not necessarily what pallet-session runs, so nothing is recorded.
+            let validators = SelectedValidators::<T>::get().into_inner();
             let _ = CurrentValidatorVoteStats::<T>::drain();
-            for validator in sorted_candidates.iter() {
+            for validator in validators.iter() {
                 let total_votes_opt = NodeAccumulativeVotes::<T>::get(validator.clone());
                 if total_votes_opt.is_none() {
                     continue;
                 }
                 let total_votes = total_votes_opt.unwrap();
-                let self_votes = total_votes.saturating_sub(NodeToUserVotesTotals::<T>::get((
-                    validator.clone(),
-                    validator.clone(),
-                )));
+                let self_votes =
+                    total_votes.saturating_sub(Self::delegation_weight(validator, validator));
 
                 let _ = CurrentValidatorVoteStats::<T>::insert(
                     validator.clone(),
@@ -725,21 +2843,44 @@ pub mod pallet {
                         total_votes,
                         self_votes,
                         delegated_votes: total_votes.saturating_sub(self_votes),
+                        performance: ValidatorPerformance::default(),
                     },
                 );
             }
         }
         fn end_session(end_index: SessionIndex) {
-            let _ = CurrentValidatorVoteStats::<T>::drain();
+            let mut validator_performance: Vec<(T::AccountId, ValidatorPerformance)> = Vec::new();
+            let mut validator_stats: Vec<ValidatorVoteStats<T>> = Vec::new();
+            for (validator, mut stats) in CurrentValidatorVoteStats::<T>::drain() {
+                stats.performance.heartbeat_received =
+                    T::ValidatorHeartbeats::is_online(&validator);
+                validator_performance.push((validator, stats.performance.clone()));
+                validator_stats.push(stats);
+            }
+            Self::archive_session(end_index, validator_stats);
             let sorted_nodes_with_votes = Self::get_sorted_candidates_with_votes();
-            // match sorted_node_list_opt {
-            //     Some(sorted_node_list) => {
-            //         let _ = T::NodeRewardManager::update_rewards(end_index, sorted_node_list);
-            //     }
-            //     None => {}
-            // }
-
-            let _ = T::NodeRewardManager::update_rewards(end_index, sorted_nodes_with_votes);
+            // the reward manager may call a contract, so its cost is known only afterwards
+            let reward_weight = T::NodeRewardManager::update_rewards(
+                end_index,
+                sorted_nodes_with_votes,
+                validator_performance,
+            );
+            frame_system::Pallet::<T>::register_extra_weight_unchecked(
+                reward_weight,
+                DispatchClass::Mandatory,
+            );
+        }
+    }
+
+    impl<T: Config> pallet_authorship::EventHandler<T::AccountId, T::BlockNumber> for Pallet<T> {
+        /// count the block towards the author's performance in the current session
+        fn note_author(author: T::AccountId) {
+            CurrentValidatorVoteStats::<T>::mutate(author, |stats_opt| {
+                if let Some(stats) = stats_opt {
+                    stats.performance.blocks_authored =
+                        stats.performance.blocks_authored.saturating_add(1);
+                }
+            });
         }
     }
 }
@@ -0,0 +1,210 @@
+use super::*;
+use frame_support::{
+    pallet_prelude::*,
+    traits::{GetStorageVersion, OnRuntimeUpgrade},
+    weights::Weight,
+};
+#[cfg(feature = "try-runtime")]
+use sp_std::vec::Vec;
+
+/// adds performance metrics to the stats of the current validators
+pub mod v2 {
+    use super::*;
+
+    #[derive(Decode)]
+    struct OldValidatorVoteStats<AccountId> {
+        account_id: AccountId,
+        total_votes: u64,
+        self_votes: u64,
+        delegated_votes: u64,
+    }
+
+    pub struct MigrateToV2<T>(PhantomData<T>);
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let onchain_version = Pallet::<T>::on_chain_storage_version();
+            if onchain_version != 1 {
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut translated: u64 = 0;
+            CurrentValidatorVoteStats::<T>::translate::<OldValidatorVoteStats<T::AccountId>, _>(
+                |_, old_stats| {
+                    translated = translated.saturating_add(1);
+                    Some(ValidatorVoteStats {
+                        account_id: old_stats.account_id,
+                        total_votes: old_stats.total_votes,
+                        self_votes: old_stats.self_votes,
+                        delegated_votes: old_stats.delegated_votes,
+                        performance: ValidatorPerformance::default(),
+                    })
+                },
+            );
+            StorageVersion::new(2).put::<Pallet<T>>();
+            T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+        }
+
+        /// the number of validator stats, which all have to decode after the upgrade
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+            let stats = CurrentValidatorVoteStats::<T>::iter_keys().count() as u32;
+            Ok((Pallet::<T>::on_chain_storage_version() == 1, stats).encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+            let (migrated, stats): (bool, u32) =
+                Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+            if !migrated {
+                return Ok(());
+            }
+            ensure!(
+                CurrentValidatorVoteStats::<T>::iter().count() as u32 == stats,
+                "validator stats were lost or do not decode"
+            );
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() == 2,
+                "storage version was not set to 2"
+            );
+            Ok(())
+        }
+    }
+}
+
+/// extends candidate metadata with contact details, commission history and session keys
+pub mod v3 {
+    use super::*;
+
+    #[derive(Decode)]
+    struct OldNodeMetadataStruct {
+        name: BoundedVec<u8, ConstU32<128>>,
+        sharing_percent: u8,
+        index_of_last_percent_change: u32,
+    }
+
+    pub struct MigrateToV3<T>(PhantomData<T>);
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV3<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let onchain_version = Pallet::<T>::on_chain_storage_version();
+            if onchain_version != 2 {
+                return T::DbWeight::get().reads(1);
+            }
+
+            // existing candidates keep their metadata without a deposit until they next edit it
+            let mut translated: u64 = 0;
+            NodeMetadata::<T>::translate::<OldNodeMetadataStruct, _>(|_, old_metadata| {
+                translated = translated.saturating_add(1);
+                let mut metadata = NodeMetadataStruct {
+                    name: old_metadata.name,
+                    sharing_percent: old_metadata.sharing_percent,
+                    index_of_last_percent_change: old_metadata.index_of_last_percent_change,
+                    website: BoundedVec::default(),
+                    contact: BoundedVec::default(),
+                    region: BoundedVec::default(),
+                    hardware: BoundedVec::default(),
+                    commission_history: BoundedVec::default(),
+                    session_keys: BoundedVec::default(),
+                };
+                metadata.record_commission_change(
+                    old_metadata.index_of_last_percent_change,
+                    old_metadata.sharing_percent,
+                );
+                Some(metadata)
+            });
+            StorageVersion::new(3).put::<Pallet<T>>();
+            T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+        }
+
+        /// the number of candidates with metadata, which all have to decode after the upgrade
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+            let candidates = NodeMetadata::<T>::iter_keys().count() as u32;
+            Ok((Pallet::<T>::on_chain_storage_version() == 2, candidates).encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+            let (migrated, candidates): (bool, u32) =
+                Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+            if !migrated {
+                return Ok(());
+            }
+            ensure!(
+                NodeMetadata::<T>::iter().count() as u32 == candidates,
+                "candidate metadata was lost or does not decode"
+            );
+            ensure!(
+                NodeMetadataDeposits::<T>::iter_keys().next().is_none(),
+                "existing candidates were charged a deposit"
+            );
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() == 3,
+                "storage version was not set to 3"
+            );
+            Ok(())
+        }
+    }
+}
+
+/// records the bootstrap votes of genesis candidates
+pub mod v4 {
+    use super::*;
+
+    pub struct MigrateToV4<T>(PhantomData<T>);
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV4<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let onchain_version = Pallet::<T>::on_chain_storage_version();
+            if onchain_version != 3 {
+                return T::DbWeight::get().reads(1);
+            }
+
+            // genesis candidates were given votes that no delegation backs. whatever part of a
+            // total no delegation accounts for is recorded as bootstrap votes, up to the amount
+            // genesis handed out.
+            let mut candidates_read: u64 = 0;
+            let mut delegations_read: u64 = 0;
+            let mut recorded: u64 = 0;
+            for (candidate, total_votes) in NodeAccumulativeVotes::<T>::iter() {
+                candidates_read = candidates_read.saturating_add(1);
+                let delegated_votes = NodeToUserVotesTotals::<T>::iter_prefix((candidate.clone(),))
+                    .fold(0u64, |total, (delegator, _)| {
+                        delegations_read = delegations_read.saturating_add(2);
+                        total.saturating_add(Pallet::<T>::delegation_weight(&delegator, &candidate))
+                    });
+                let bootstrap_votes = total_votes
+                    .saturating_sub(delegated_votes)
+                    .min(BOOTSTRAP_VOTES);
+                if bootstrap_votes > 0 {
+                    BootstrapVotes::<T>::insert(candidate, bootstrap_votes);
+                    recorded = recorded.saturating_add(1);
+                }
+            }
+            StorageVersion::new(4).put::<Pallet<T>>();
+            T::DbWeight::get().reads_writes(candidates_read + delegations_read + 1, recorded + 1)
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+            ensure!(
+                BootstrapVotes::<T>::iter_keys().next().is_none(),
+                "bootstrap votes are already recorded"
+            );
+            Ok((Pallet::<T>::on_chain_storage_version() == 3).encode())
+        }
+
+        /// the recorded bootstrap votes have to leave the vote invariants intact
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+            let migrated: bool =
+                Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+            if !migrated {
+                return Ok(());
+            }
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() == 4,
+                "storage version was not set to 4"
+            );
+            Pallet::<T>::do_try_state()
+        }
+    }
+}
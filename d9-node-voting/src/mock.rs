@@ -0,0 +1,242 @@
+use crate as pallet_d9_node_voting;
+use crate::{NodeRewardManager, ValidatorHeartbeats, ValidatorPerformance};
+use frame_support::{
+    parameter_types,
+    traits::{
+        ConstBool, ConstU32, ConstU64, ConstU8, Everything, GenesisBuild, Nothing, Randomness, Time,
+    },
+    weights::Weight,
+};
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, IdentityLookup},
+};
+use sp_staking::SessionIndex;
+use sp_std::cell::RefCell;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+pub const ALICE: u64 = 1;
+pub const BOB: u64 = 2;
+pub const CHARLIE: u64 = 3;
+pub const DAVE: u64 = 4;
+/// candidate created at genesis, with bootstrap votes and no metadata
+pub const GENESIS_NODE: u64 = 10;
+
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        Contracts: pallet_contracts,
+        NodeVoting: pallet_d9_node_voting,
+    }
+);
+
+parameter_types! {
+    pub BlockWeights: frame_system::limits::BlockWeights =
+        frame_system::limits::BlockWeights::simple_max(
+            Weight::from_parts(2_000_000_000_000, u64::MAX),
+        );
+    pub ContractsSchedule: pallet_contracts::Schedule<Test> = Default::default();
+    pub MaxBurnContractGas: Weight = Weight::from_parts(10_000_000_000, 1_000_000);
+}
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = Everything;
+    type BlockWeights = BlockWeights;
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+    type Balance = u64;
+    type RuntimeEvent = RuntimeEvent;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU64<1>;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type HoldIdentifier = ();
+    type FreezeIdentifier = ();
+    type MaxHolds = ();
+    type MaxFreezes = ();
+}
+
+/// contracts are never instantiated in these tests, so time and randomness stay constant
+pub struct MockTime;
+impl Time for MockTime {
+    type Moment = u64;
+    fn now() -> u64 {
+        0
+    }
+}
+
+pub struct MockRandomness;
+impl Randomness<H256, u64> for MockRandomness {
+    fn random(_subject: &[u8]) -> (H256, u64) {
+        (H256::zero(), 0)
+    }
+}
+
+impl pallet_contracts::Config for Test {
+    type Time = MockTime;
+    type Randomness = MockRandomness;
+    type Currency = Balances;
+    type RuntimeEvent = RuntimeEvent;
+    type RuntimeCall = RuntimeCall;
+    type CallFilter = Nothing;
+    type WeightPrice = ();
+    type WeightInfo = ();
+    type ChainExtension = ();
+    type Schedule = ContractsSchedule;
+    type CallStack = [pallet_contracts::Frame<Self>; 5];
+    type DepositPerByte = ConstU64<1>;
+    type DefaultDepositLimit = ConstU64<1_000_000>;
+    type DepositPerItem = ConstU64<1>;
+    type AddressGenerator = pallet_contracts::DefaultAddressGenerator;
+    type MaxCodeLen = ConstU32<{ 123 * 1024 }>;
+    type MaxStorageKeyLen = ConstU32<128>;
+    type UnsafeUnstableInterface = ConstBool<false>;
+    type MaxDebugBufferLen = ConstU32<{ 2 * 1024 * 1024 }>;
+}
+
+/// session, ranked nodes and validator performance of one `update_rewards` call
+pub type RewardUpdate = (
+    SessionIndex,
+    Vec<(u64, u64)>,
+    Vec<(u64, ValidatorPerformance)>,
+);
+
+thread_local! {
+    static REWARD_UPDATES: RefCell<Vec<RewardUpdate>> = RefCell::new(Vec::new());
+    static SETTLEMENTS: RefCell<Vec<(u64, u64, u64)>> = RefCell::new(Vec::new());
+    static OFFLINE_VALIDATORS: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+}
+
+/// records what the pallet hands to the reward manager
+pub struct MockRewardManager;
+impl NodeRewardManager<u64> for MockRewardManager {
+    fn update_rewards(
+        end_index: SessionIndex,
+        nodes_with_votes: Vec<(u64, u64)>,
+        validator_performance: Vec<(u64, ValidatorPerformance)>,
+    ) -> Weight {
+        REWARD_UPDATES.with(|updates| {
+            updates
+                .borrow_mut()
+                .push((end_index, nodes_with_votes, validator_performance))
+        });
+        Weight::zero()
+    }
+
+    fn on_delegation_weight_change(voter: &u64, candidate: &u64, previous_weight: u64) {
+        SETTLEMENTS.with(|settlements| {
+            settlements
+                .borrow_mut()
+                .push((*voter, *candidate, previous_weight))
+        });
+    }
+}
+
+pub fn reward_updates() -> Vec<RewardUpdate> {
+    REWARD_UPDATES.with(|updates| updates.borrow().clone())
+}
+
+/// (voter, candidate, previous weight) of every settlement the reward manager was asked for
+pub fn settlements() -> Vec<(u64, u64, u64)> {
+    SETTLEMENTS.with(|settlements| settlements.borrow().clone())
+}
+
+pub struct MockHeartbeats;
+impl ValidatorHeartbeats<u64> for MockHeartbeats {
+    fn is_online(validator: &u64) -> bool {
+        OFFLINE_VALIDATORS.with(|offline| !offline.borrow().contains(validator))
+    }
+}
+
+pub fn set_offline(validator: u64) {
+    OFFLINE_VALIDATORS.with(|offline| offline.borrow_mut().push(validator));
+}
+
+impl pallet_d9_node_voting::Config for Test {
+    type CurrencySubUnits = ConstU64<1>;
+    type Currency = Balances;
+    type RuntimeEvent = RuntimeEvent;
+    type MaxCandidates = ConstU32<10>;
+    type MaxValidatorNodes = ConstU32<5>;
+    type NodeRewardManager = MockRewardManager;
+    type MaxDelegationPolicyEntries = ConstU32<4>;
+    type ValidatorHeartbeats = MockHeartbeats;
+    type HistoryDepth = ConstU32<4>;
+    type SharePercentNoticePeriod = ConstU32<2>;
+    type MaxSharePercentDrop = ConstU8<10>;
+    type MaxBurnContractGas = MaxBurnContractGas;
+    type ConvictionPeriod = ConstU32<2>;
+    type MetadataDepositBase = ConstU64<10>;
+    type MetadataDepositPerByte = ConstU64<0>;
+    type SessionKeysRegistered = Everything;
+    type RetirementNoticePeriod = ConstU32<2>;
+    type JailDuration = ConstU32<3>;
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut storage = frame_system::GenesisConfig::default()
+        .build_storage::<Test>()
+        .unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![
+            (ALICE, 100_000),
+            (BOB, 100_000),
+            (CHARLIE, 100_000),
+            (DAVE, 100_000),
+            (GENESIS_NODE, 100_000),
+        ],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+    pallet_d9_node_voting::GenesisConfig::<Test> {
+        initial_candidates: vec![GENESIS_NODE],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
+
+/// run `test` and check the vote invariants once it is done
+pub fn build_and_execute(test: impl FnOnce()) {
+    new_test_ext().execute_with(|| {
+        test();
+        NodeVoting::do_try_state().unwrap();
+    });
+}
@@ -0,0 +1,33 @@
+use crate::{ArchivedValidator, CandidateInfo};
+use codec::Codec;
+use sp_arithmetic::Perquintill;
+use sp_staking::SessionIndex;
+use sp_std::prelude::*;
+
+sp_api::decl_runtime_apis! {
+    pub trait NodeVotingApi<AccountId, Balance> where AccountId: Codec, Balance: Codec {
+        /// all candidates with their accumulated votes, highest first
+        fn get_sorted_candidates_with_votes() -> Vec<(AccountId, u64)>;
+
+        /// candidates with votes and metadata, highest votes first, paginated
+        fn get_candidates(offset: u32, limit: u32) -> Vec<CandidateInfo<AccountId>>;
+
+        /// validators that would be elected if the next session was planned now
+        fn get_projected_validators() -> Vec<AccountId>;
+
+        /// candidates a user has delegated votes to
+        fn get_user_supported_nodes(user: AccountId) -> Vec<AccountId>;
+
+        /// share of a candidate's votes that came from the user
+        fn get_user_support_ratio(user: AccountId, candidate: AccountId) -> Option<Perquintill>;
+
+        /// percentage of a node's rewards shared with its supporters
+        fn get_node_sharing_percent(node: AccountId) -> Option<u8>;
+
+        /// votes `payer` would buy by spending `amount` on voting interest
+        fn preview_voting_interest(payer: AccountId, amount: Balance) -> u64;
+
+        /// elected validators of a session, `None` once the session has been pruned
+        fn get_session_archive(session_index: SessionIndex) -> Option<Vec<ArchivedValidator<AccountId>>>;
+    }
+}
@@ -3,7 +3,8 @@ use crate::pallet::Pallet;
 use crate::BalanceOf;
 use codec::MaxEncodedLen;
 use frame_support::RuntimeDebugNoBound;
-use frame_support::{ pallet_prelude::*, BoundedVec, inherent::Vec };
+use frame_support::{ pallet_prelude::*, traits::Contains, weights::Weight, BoundedVec, inherent::Vec };
+use sp_arithmetic::{Perbill, Percent};
 use sp_runtime::traits::Convert;
 use sp_staking::SessionIndex;
 
@@ -60,6 +61,42 @@ pub struct ValidatorDelegations<T: Config> {
     #[codec(compact)]
     pub votes: u64,
 }
+#[derive(
+    PartialEqNoBound,
+    EqNoBound,
+    CloneNoBound,
+    Encode,
+    Decode,
+    RuntimeDebugNoBound,
+    TypeInfo,
+    MaxEncodedLen
+)]
+/// part of a delegation moved from one candidate to another
+#[scale_info(skip_type_params(T))]
+pub struct VoteMove<T: Config> {
+    pub from: T::AccountId,
+    pub to: T::AccountId,
+    #[codec(compact)]
+    pub votes: u64,
+}
+
+#[derive(
+    PartialEqNoBound,
+    EqNoBound,
+    CloneNoBound,
+    Encode,
+    Decode,
+    RuntimeDebugNoBound,
+    TypeInfo,
+    MaxEncodedLen
+)]
+/// share of newly acquired votes that a user's delegation policy sends to a candidate
+#[scale_info(skip_type_params(T))]
+pub struct DelegationPolicyEntry<T: Config> {
+    pub candidate: T::AccountId,
+    pub share: Percent,
+}
+
 #[derive(
     PartialEqNoBound,
     EqNoBound,
@@ -77,6 +114,63 @@ pub struct Candidate<T: Config> {
     total_votes: BalanceOf<T>,
 }
 
+/// account allowed to manage another account's delegations, within the owner's limits
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct VoteManager<AccountId> {
+    pub manager: AccountId,
+    /// most votes the manager may move in total during one session
+    pub max_votes_per_session: u64,
+    /// session from which the manager can no longer act, `None` for no expiry
+    pub expires_at: Option<SessionIndex>,
+}
+
+/// delegation change a vote manager makes on behalf of the owner
+#[derive(
+    PartialEqNoBound,
+    EqNoBound,
+    CloneNoBound,
+    Encode,
+    Decode,
+    RuntimeDebugNoBound,
+    TypeInfo
+)]
+#[scale_info(skip_type_params(T))]
+pub enum ManagedVoteCall<T: Config> {
+    DelegateVotes(Vec<ValidatorDelegations<T>>),
+    RemoveVotes {
+        candidate: T::AccountId,
+        votes: u64,
+    },
+    RedistributeVotes {
+        from: T::AccountId,
+        to: T::AccountId,
+    },
+    RedistributeVotesBatch(Vec<VoteMove<T>>),
+}
+
+impl<T: Config> ManagedVoteCall<T> {
+    /// delegations the call can change
+    pub fn delegations_changed(&self) -> u64 {
+        match self {
+            ManagedVoteCall::DelegateVotes(delegations) => delegations.len() as u64,
+            ManagedVoteCall::RemoveVotes { .. } => 1,
+            ManagedVoteCall::RedistributeVotes { .. } => 2,
+            ManagedVoteCall::RedistributeVotesBatch(moves) => {
+                (moves.len() as u64).saturating_mul(2)
+            }
+        }
+    }
+}
+
 pub struct ConvertAccountId<T: Config>(PhantomData<T>);
 impl<T: Config> Convert<T::AccountId, Option<T::AccountId>> for ConvertAccountId<T> {
     fn convert(account_id: T::AccountId) -> Option<T::AccountId> {
@@ -84,6 +178,27 @@ impl<T: Config> Convert<T::AccountId, Option<T::AccountId>> for ConvertAccountId
     }
 }
 
+/// maps the account managing a node to the stash it is elected as
+///
+/// runtimes whose nodes use controllers opt into it as `ValidatorIdOf` instead of
+/// `ConvertAccountId`
+pub struct StashOf<T: Config>(PhantomData<T>);
+impl<T: Config> Convert<T::AccountId, Option<T::AccountId>> for StashOf<T> {
+    fn convert(account_id: T::AccountId) -> Option<T::AccountId> {
+        Some(Pallet::<T>::stash_of(&account_id))
+    }
+}
+
+/// accounts that have session keys queued in pallet-session
+pub struct RegisteredSessionKeys<T>(PhantomData<T>);
+impl<T: pallet_session::Config> Contains<T::AccountId> for RegisteredSessionKeys<T> {
+    fn contains(account_id: &T::AccountId) -> bool {
+        T::ValidatorIdOf::convert(account_id.clone())
+            .map(|validator_id| pallet_session::NextKeys::<T>::contains_key(validator_id))
+            .unwrap_or(false)
+    }
+}
+
 #[derive(
     PartialEq,
     Eq,
@@ -102,6 +217,28 @@ pub struct ValidatorVoteStats<T: Config> {
     pub total_votes: u64,
     pub self_votes: u64,
     pub delegated_votes: u64,
+    pub performance: ValidatorPerformance,
+}
+
+/// what a validator actually did during a session
+#[derive(
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Default,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct ValidatorPerformance {
+    /// blocks authored by the validator in the session
+    pub blocks_authored: u32,
+    /// whether the validator reported itself online during the session
+    pub heartbeat_received: bool,
 }
 
 #[derive(
@@ -120,6 +257,321 @@ pub struct NodeMetadataStruct {
     pub name: BoundedVec<u8, ConstU32<128>>,
     pub sharing_percent: u8,
     pub index_of_last_percent_change: u32,
+    pub website: BoundedVec<u8, ConstU32<128>>,
+    /// contact handle, e.g. an email address or chat username
+    pub contact: BoundedVec<u8, ConstU32<64>>,
+    pub region: BoundedVec<u8, ConstU32<64>>,
+    pub hardware: BoundedVec<u8, ConstU32<256>>,
+    /// (session, sharing percent) of the most recent supporter share changes, oldest first
+    pub commission_history: BoundedVec<(SessionIndex, u8), ConstU32<16>>,
+    /// encoded session keys the candidate runs its node with
+    pub session_keys: BoundedVec<u8, ConstU32<256>>,
+}
+
+impl NodeMetadataStruct {
+    /// record a supporter share change, dropping the oldest entry once the history is full
+    pub fn record_commission_change(&mut self, session_index: SessionIndex, sharing_percent: u8) {
+        if self.commission_history.is_full() {
+            self.commission_history.remove(0);
+        }
+        let _ = self.commission_history.try_push((session_index, sharing_percent));
+    }
+}
+
+/// an elected validator as recorded in the session archive
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct ArchivedValidator<AccountId> {
+    pub account_id: AccountId,
+    pub total_votes: u64,
+    pub self_votes: u64,
+    pub delegated_votes: u64,
+    pub sharing_percent: u8,
+    pub performance: ValidatorPerformance,
+}
+
+/// a candidate together with its votes and metadata, as listed to frontends
+#[derive(PartialEq, Eq, Clone, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct CandidateInfo<AccountId> {
+    pub account_id: AccountId,
+    pub total_votes: u64,
+    pub metadata: Option<NodeMetadataStruct>,
+    pub status: CandidateStatus,
+}
+
+/// lifecycle state of a candidate, only active candidates can be elected
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Default,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub enum CandidateStatus {
+    #[default]
+    Active,
+    /// keeps its votes but sits out elections until it unchills
+    Chilled,
+    /// excluded from elections after an offence until session `until`
+    ///
+    /// `chilled` records whether the candidate was chilled when it was jailed, it returns
+    /// to that state once the jail ends
+    Jailed {
+        until: SessionIndex,
+        chilled: bool,
+    },
+    /// removed from the candidates in session `effective`
+    Retiring {
+        effective: SessionIndex,
+    },
+}
+
+/// a sharing percentage change that takes effect once its notice period has passed
+#[derive(
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct PendingSharePercentChange {
+    pub sharing_percent: u8,
+    pub effective_session: SessionIndex,
+}
+
+/// logical operations the runtime performs through a contract
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub enum ContractOperation {
+    /// node reward contract, called with the session index and the ranked nodes
+    UpdateRewards,
+    /// main pool contract, called with the voter and the burn contract
+    BurnForVotes,
+}
+
+impl ContractOperation {
+    /// selector the operation used before the registry existed
+    pub fn legacy_selector(&self) -> [u8; 4] {
+        match self {
+            //0x93440f8d
+            ContractOperation::UpdateRewards => [0x93, 0x44, 0x0f, 0x8d],
+            //0xb1efc17b
+            ContractOperation::BurnForVotes => [0xb1, 0xef, 0xc1, 0x7b],
+        }
+    }
+}
+
+/// where and how a contract operation is called
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct ContractEntry<AccountId> {
+    pub address: AccountId,
+    pub selector: [u8; 4],
+    /// bumped whenever the contract's message changes, for clients reading the registry
+    pub abi_version: u32,
+}
+
+impl<AccountId> ContractEntry<AccountId> {
+    /// call data for the entry's message with already encoded arguments
+    pub fn call_data(&self, mut encoded_args: Vec<u8>) -> Vec<u8> {
+        let mut data: Vec<u8> = self.selector.to_vec();
+        data.append(&mut encoded_args);
+        data
+    }
+}
+
+/// what happens to tokens spent on voting interest when no contract hook is registered
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Default,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub enum BurnDestination<AccountId> {
+    /// tokens are removed from the total issuance
+    #[default]
+    Burn,
+    /// tokens are transferred to the given account
+    Account(AccountId),
+}
+
+/// rate applied to the part of a voter's lifetime spending at or above `threshold`
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct PricingTier<Balance> {
+    pub threshold: Balance,
+    /// fraction of the base rate of one vote per currency unit
+    pub rate: Perbill,
+}
+
+/// bonus on votes bought before `until_session`
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct TimeMultiplier {
+    pub until_session: SessionIndex,
+    pub bonus: Percent,
+}
+
+/// how many votes a purchase of voting interest buys
+///
+/// spending below the first tier threshold gets the base rate. with no tiers and no
+/// multipliers every currency unit buys exactly one vote.
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Default,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct VotingInterestPricing<Balance> {
+    /// tiers sorted by ascending threshold
+    pub tiers: BoundedVec<PricingTier<Balance>, ConstU32<16>>,
+    /// multipliers sorted by ascending session, the first one still running applies
+    pub time_multipliers: BoundedVec<TimeMultiplier, ConstU32<8>>,
+}
+
+/// how many lock periods a delegation is committed for
+///
+/// while the lock runs the delegation counts `n` times its votes and does not decay
+#[derive(
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    Default,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub enum Conviction {
+    #[default]
+    None,
+    Locked1x,
+    Locked2x,
+    Locked3x,
+    Locked4x,
+    Locked5x,
+    Locked6x,
+}
+
+impl Conviction {
+    pub fn lock_periods(self) -> u32 {
+        match self {
+            Conviction::None => 0,
+            Conviction::Locked1x => 1,
+            Conviction::Locked2x => 2,
+            Conviction::Locked3x => 3,
+            Conviction::Locked4x => 4,
+            Conviction::Locked5x => 5,
+            Conviction::Locked6x => 6,
+        }
+    }
+
+    pub fn multiplier(self) -> u64 {
+        (self.lock_periods() as u64).max(1)
+    }
+}
+
+/// conviction and decay state of the votes one user delegated to one candidate
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct DelegationWeight {
+    /// weight the delegation currently adds to the candidate's accumulated votes
+    pub effective_votes: u64,
+    pub conviction: Conviction,
+    pub locked_until: SessionIndex,
+    pub refreshed_at: SessionIndex,
+}
+
+/// how fast delegations that are not locked lose weight
+#[derive(
+    PartialEq,
+    Eq,
+    Clone,
+    Default,
+    Encode,
+    Decode,
+    RuntimeDebug,
+    TypeInfo,
+    MaxEncodedLen
+)]
+pub struct VoteDecayParameters {
+    /// weight lost every session since the delegation was last refreshed
+    pub decay_per_session: Perbill,
+    /// share of the votes that is never decayed away
+    pub floor: Perbill,
 }
 
 pub struct ValidatorStatsOf<T: Config>(PhantomData<T>);
@@ -130,5 +582,78 @@ impl<T: Config> Convert<T::AccountId, Option<ValidatorVoteStats<T>>> for Validat
 }
 
 pub trait NodeRewardManager<AccountId> {
-    fn update_rewards(end_index: SessionIndex, nodes_with_votes: Vec<(AccountId, u64)>) -> ();
+    fn update_rewards(
+        end_index: SessionIndex,
+        nodes_with_votes: Vec<(AccountId, u64)>,
+        validator_performance: Vec<(AccountId, ValidatorPerformance)>,
+    ) -> Weight;
+
+    /// called before the weight `voter` gives `candidate` changes, with the weight it had so far
+    fn on_delegation_weight_change(_voter: &AccountId, _candidate: &AccountId, _previous_weight: u64) {}
+
+    /// called after `voter` withdrew the last of its votes from `candidate`, once the
+    /// delegation was settled through `on_delegation_weight_change`
+    fn on_delegation_removed(_voter: &AccountId, _candidate: &AccountId) {}
+
+    /// called after `candidate` was removed together with all its delegations
+    fn on_candidate_removed(_candidate: &AccountId) {}
+
+    /// weight of one `on_delegation_weight_change` call
+    fn delegation_weight_change_weight() -> Weight {
+        Weight::zero()
+    }
+}
+
+/// voting data a reward manager works from, so it does not need this pallet's config
+pub trait NodeVotingProvider<AccountId> {
+    /// session that is currently running
+    fn current_session() -> SessionIndex;
+
+    /// most candidates there can be, and so the most a voter can delegate to
+    fn max_candidates() -> u32;
+
+    /// accumulated votes of `node`, 0 when it is not a candidate
+    fn node_votes(node: &AccountId) -> u64;
+
+    /// votes of `node` that delegations account for, its total without the bootstrap votes
+    fn delegated_votes(node: &AccountId) -> u64;
+
+    /// weight the delegation of `delegator` currently adds to `candidate`
+    fn delegation_weight(delegator: &AccountId, candidate: &AccountId) -> u64;
+
+    /// candidates `delegator` has delegated votes to
+    fn delegations_of(delegator: &AccountId) -> Vec<AccountId>;
+
+    /// percentage of `node`'s rewards shared with its supporters
+    fn supporter_share(node: &AccountId) -> u8;
+
+    /// whether `node` was jailed at any point of `session`
+    ///
+    /// reads the last session the node was recorded jailed in, which is kept for every session
+    /// a jail lasts
+    fn was_jailed_in(node: &AccountId, session: SessionIndex) -> bool;
+
+    /// contract registered for `operation`
+    fn contract_entry(operation: ContractOperation) -> Option<ContractEntry<AccountId>>;
+
+    /// register or clear the contract of `operation`, a new entry is dry run with `call_args`
+    /// as `caller`, or as the contract itself without one. the caller's origin is not checked.
+    fn set_contract_entry(
+        operation: ContractOperation,
+        entry: Option<ContractEntry<AccountId>>,
+        caller: Option<AccountId>,
+        call_args: Vec<u8>,
+    ) -> DispatchResult;
+
+    /// weight of `set_contract_entry`
+    fn set_contract_entry_weight() -> Weight;
+}
+
+/// source of liveness information for validators, usually backed by pallet-im-online
+///
+/// there is deliberately no implementation for `()`: a default that reports every validator
+/// as online would make the offline signal meaningless, so the runtime has to supply one.
+pub trait ValidatorHeartbeats<AccountId> {
+    /// whether `validator` has sent a heartbeat during the current session
+    fn is_online(validator: &AccountId) -> bool;
 }
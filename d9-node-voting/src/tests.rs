@@ -0,0 +1,1159 @@
+use crate::{mock::*, *};
+use frame_support::{assert_noop, assert_ok, traits::Hooks, weights::Weight, BoundedVec};
+use pallet_authorship::EventHandler;
+use pallet_session::SessionManager;
+use sp_arithmetic::{Perbill, Percent};
+use sp_runtime::traits::{BadOrigin, Convert};
+use sp_staking::{
+    offence::{DisableStrategy, OffenceDetails, OnOffenceHandler},
+    SessionIndex,
+};
+
+fn metadata(sharing_percent: u8) -> NodeMetadataStruct {
+    NodeMetadataStruct {
+        name: BoundedVec::truncate_from(b"node".to_vec()),
+        sharing_percent,
+        index_of_last_percent_change: 0,
+        website: BoundedVec::default(),
+        contact: BoundedVec::default(),
+        region: BoundedVec::default(),
+        hardware: BoundedVec::default(),
+        commission_history: BoundedVec::default(),
+        session_keys: BoundedVec::default(),
+    }
+}
+
+fn submit_candidacy(candidate: u64) {
+    assert_ok!(NodeVoting::submit_candidacy(
+        RuntimeOrigin::signed(candidate),
+        metadata(50)
+    ));
+}
+
+fn buy_votes(voter: u64, amount: u64) {
+    assert_ok!(NodeVoting::purchase_voting_interest(
+        RuntimeOrigin::signed(voter),
+        voter,
+        amount
+    ));
+}
+
+fn delegate(voter: u64, candidate: u64, votes: u64) {
+    assert_ok!(NodeVoting::delegate_votes(
+        RuntimeOrigin::signed(voter),
+        vec![ValidatorDelegations { candidate, votes }]
+    ));
+}
+
+/// start `session` and let the idle hook bring delegation weights up to date
+fn start_session(session: SessionIndex) {
+    NodeVoting::start_session(session);
+    NodeVoting::on_idle(1, Weight::MAX);
+}
+
+fn vote_move(from: u64, to: u64, votes: u64) -> VoteMove<Test> {
+    VoteMove { from, to, votes }
+}
+
+fn report_offence(offender: u64) {
+    let offence = OffenceDetails {
+        offender: (offender, ()),
+        reporters: vec![],
+    };
+    <NodeVoting as OnOffenceHandler<u64, (u64, ()), Weight>>::on_offence(
+        &[offence],
+        &[Perbill::zero()],
+        0,
+        DisableStrategy::Never,
+    );
+}
+
+fn policy_entry(candidate: u64, share: u8) -> DelegationPolicyEntry<Test> {
+    DelegationPolicyEntry {
+        candidate,
+        share: Percent::from_percent(share),
+    }
+}
+
+fn node_votes(node: u64) -> u64 {
+    NodeAccumulativeVotes::<Test>::get(node).unwrap_or(0)
+}
+
+#[test]
+fn delegation_policy_splits_new_voting_interest() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        submit_candidacy(CHARLIE);
+        buy_votes(BOB, 100);
+        assert_ok!(NodeVoting::set_delegation_policy(
+            RuntimeOrigin::signed(BOB),
+            vec![policy_entry(ALICE, 70), policy_entry(CHARLIE, 30)]
+        ));
+        // the votes bob already had are delegated right away
+        assert_eq!(node_votes(ALICE), 70);
+        assert_eq!(node_votes(CHARLIE), 30);
+
+        // the rounding dust goes to the first entry
+        buy_votes(BOB, 101);
+        assert_eq!(node_votes(ALICE), 141);
+        assert_eq!(node_votes(CHARLIE), 60);
+        assert_eq!(
+            UsersVotingInterests::<Test>::get(BOB).unwrap().delegated,
+            201
+        );
+
+        assert_ok!(NodeVoting::clear_delegation_policy(RuntimeOrigin::signed(
+            BOB
+        )));
+        buy_votes(BOB, 100);
+        assert_eq!(
+            UsersVotingInterests::<Test>::get(BOB).unwrap().delegated,
+            201
+        );
+    });
+}
+
+#[test]
+fn delegation_policy_must_be_a_valid_split() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        submit_candidacy(CHARLIE);
+        let set_policy =
+            |policy| NodeVoting::set_delegation_policy(RuntimeOrigin::signed(BOB), policy);
+        assert_noop!(set_policy(vec![]), Error::<Test>::EmptyDelegationList);
+        assert_noop!(
+            set_policy(vec![policy_entry(ALICE, 60), policy_entry(CHARLIE, 30)]),
+            Error::<Test>::DelegationPolicySharesMustTotal100
+        );
+        assert_noop!(
+            set_policy(vec![policy_entry(ALICE, 50), policy_entry(ALICE, 50)]),
+            Error::<Test>::DuplicateCandidateInDelegationPolicy
+        );
+        assert_noop!(
+            set_policy(vec![policy_entry(DAVE, 100)]),
+            Error::<Test>::CandidateDoesNotExist
+        );
+        assert_noop!(
+            set_policy(vec![policy_entry(ALICE, 20); 5]),
+            Error::<Test>::DelegationPolicyTooLarge
+        );
+        assert_noop!(
+            NodeVoting::clear_delegation_policy(RuntimeOrigin::signed(BOB)),
+            Error::<Test>::NoDelegationPolicy
+        );
+    });
+}
+
+#[test]
+fn votes_freed_by_a_removed_candidate_follow_the_policy() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        submit_candidacy(CHARLIE);
+        submit_candidacy(DAVE);
+        buy_votes(BOB, 100);
+        assert_ok!(NodeVoting::set_delegation_policy(
+            RuntimeOrigin::signed(BOB),
+            vec![
+                policy_entry(ALICE, 50),
+                policy_entry(CHARLIE, 25),
+                policy_entry(DAVE, 25)
+            ]
+        ));
+
+        assert_ok!(NodeVoting::remove_candidacy(RuntimeOrigin::signed(ALICE)));
+        NodeVoting::start_session(2);
+        assert_eq!(node_votes(ALICE), 0);
+        assert_eq!(node_votes(CHARLIE), 50);
+        assert_eq!(node_votes(DAVE), 50);
+        assert_eq!(
+            NodeVoting::delegation_policy(BOB).unwrap().into_inner(),
+            vec![policy_entry(CHARLIE, 25), policy_entry(DAVE, 25)]
+        );
+        System::assert_has_event(RuntimeEvent::NodeVoting(Event::FreedVotesRebalanced(
+            BOB, ALICE, 50,
+        )));
+    });
+}
+
+#[test]
+fn session_end_hands_votes_and_performance_to_the_reward_manager() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+
+        assert_eq!(NodeVoting::new_session(1), Some(vec![GENESIS_NODE, ALICE]));
+        NodeVoting::start_session(1);
+        NodeVoting::note_author(ALICE);
+        NodeVoting::note_author(ALICE);
+        set_offline(GENESIS_NODE);
+        NodeVoting::end_session(1);
+
+        let (session, nodes_with_votes, mut performance) = reward_updates().pop().unwrap();
+        assert_eq!(session, 1);
+        assert_eq!(nodes_with_votes, vec![(GENESIS_NODE, 1_000), (ALICE, 100)]);
+        performance.sort();
+        assert_eq!(
+            performance,
+            vec![
+                (
+                    ALICE,
+                    ValidatorPerformance {
+                        blocks_authored: 2,
+                        heartbeat_received: true,
+                    }
+                ),
+                (
+                    GENESIS_NODE,
+                    ValidatorPerformance {
+                        blocks_authored: 0,
+                        heartbeat_received: false,
+                    }
+                ),
+            ]
+        );
+    });
+}
+
+#[test]
+fn only_the_last_history_depth_sessions_are_archived() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+        for session in 1..=5 {
+            NodeVoting::new_session(session);
+            NodeVoting::start_session(session);
+            NodeVoting::end_session(session);
+        }
+
+        let archive = NodeVoting::get_session_archive(5).unwrap();
+        assert_eq!(archive.len(), 2);
+        let alice = archive
+            .iter()
+            .find(|validator| validator.account_id == ALICE)
+            .unwrap();
+        assert_eq!(alice.total_votes, 100);
+        assert_eq!(alice.sharing_percent, 50);
+
+        // HistoryDepth is 4, so sessions 2 to 5 are kept
+        assert_eq!(OldestRetainedSession::<Test>::get(), 2);
+        assert_eq!(NodeVoting::get_session_archive(1), None);
+        assert!(NodeVoting::get_session_archive(2).is_some());
+        assert!(SessionNodeList::<Test>::get(1).is_none());
+        assert!(SessionNodeList::<Test>::get(2).is_some());
+    });
+}
+
+#[test]
+fn candidates_are_listed_page_by_page_in_vote_order() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        submit_candidacy(CHARLIE);
+        submit_candidacy(DAVE);
+        buy_votes(BOB, 600);
+        delegate(BOB, DAVE, 100);
+        delegate(BOB, ALICE, 300);
+        delegate(BOB, CHARLIE, 200);
+
+        let page = |offset, limit| -> Vec<(u64, u64)> {
+            NodeVoting::get_candidates_page(offset, limit)
+                .into_iter()
+                .map(|candidate| (candidate.account_id, candidate.total_votes))
+                .collect()
+        };
+        assert_eq!(page(0, 2), vec![(GENESIS_NODE, 1_000), (ALICE, 300)]);
+        assert_eq!(page(2, 2), vec![(CHARLIE, 200), (DAVE, 100)]);
+        assert_eq!(page(4, 2), vec![]);
+
+        let charlie = NodeVoting::get_candidates_page(2, 1).pop().unwrap();
+        assert_eq!(charlie.metadata.unwrap().sharing_percent, 50);
+        assert_eq!(charlie.status, CandidateStatus::Active);
+    });
+}
+
+#[test]
+fn supporter_share_changes_take_effect_after_the_notice_period() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        assert_noop!(
+            NodeVoting::change_candidate_supporter_share(RuntimeOrigin::signed(ALICE), 39),
+            Error::<Test>::SharePercentDropTooLarge
+        );
+        assert_ok!(NodeVoting::change_candidate_supporter_share(
+            RuntimeOrigin::signed(ALICE),
+            40
+        ));
+        assert_noop!(
+            NodeVoting::change_candidate_supporter_share(RuntimeOrigin::signed(ALICE), 45),
+            Error::<Test>::SharePercentChangeAlreadyPending
+        );
+
+        NodeVoting::start_session(1);
+        assert_eq!(NodeVoting::get_node_sharing_percent(ALICE), Some(50));
+        NodeVoting::start_session(2);
+        assert_eq!(NodeVoting::get_node_sharing_percent(ALICE), Some(40));
+        assert!(!PendingSharePercentChanges::<Test>::contains_key(ALICE));
+        let metadata = NodeMetadata::<Test>::get(ALICE).unwrap();
+        assert_eq!(metadata.index_of_last_percent_change, 2);
+        assert_eq!(
+            metadata.commission_history.into_inner(),
+            vec![(0, 50), (2, 40)]
+        );
+        System::assert_has_event(RuntimeEvent::NodeVoting(Event::SupporterShareChanged(
+            ALICE, 40,
+        )));
+    });
+}
+
+#[test]
+fn pending_supporter_share_change_can_be_cancelled() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        assert_noop!(
+            NodeVoting::cancel_supporter_share_change(RuntimeOrigin::signed(ALICE)),
+            Error::<Test>::NoPendingSharePercentChange
+        );
+        assert_ok!(NodeVoting::change_candidate_supporter_share(
+            RuntimeOrigin::signed(ALICE),
+            60
+        ));
+        assert_ok!(NodeVoting::cancel_supporter_share_change(
+            RuntimeOrigin::signed(ALICE)
+        ));
+
+        NodeVoting::start_session(2);
+        assert_eq!(NodeVoting::get_node_sharing_percent(ALICE), Some(50));
+    });
+}
+
+#[test]
+fn voting_interest_burns_the_tokens() {
+    build_and_execute(|| {
+        let issuance = Balances::total_issuance();
+        buy_votes(ALICE, 500);
+        assert_eq!(Balances::free_balance(ALICE), 99_500);
+        assert_eq!(Balances::total_issuance(), issuance - 500);
+        assert_eq!(
+            UsersVotingInterests::<Test>::get(ALICE),
+            Some(VotingInterest::new(500))
+        );
+        System::assert_has_event(RuntimeEvent::NodeVoting(Event::VotingInterestAdded(
+            ALICE, ALICE, 500, 500,
+        )));
+    });
+}
+
+#[test]
+fn voting_interest_below_the_minimum_burn_is_rejected() {
+    build_and_execute(|| {
+        assert_noop!(
+            NodeVoting::purchase_voting_interest(RuntimeOrigin::signed(ALICE), ALICE, 99),
+            Error::<Test>::BurnAmountBelowMinimum
+        );
+        assert_ok!(NodeVoting::set_minimum_burn_amount(
+            RuntimeOrigin::root(),
+            10
+        ));
+        buy_votes(ALICE, 10);
+    });
+}
+
+#[test]
+fn legacy_purchase_only_goes_through_the_registered_contracts() {
+    build_and_execute(|| {
+        assert_noop!(
+            NodeVoting::add_voting_interest(RuntimeOrigin::signed(ALICE), ALICE, BOB, 500, CHARLIE),
+            Error::<Test>::BurnContractMismatch
+        );
+    });
+}
+
+#[test]
+fn voting_interest_is_priced_over_the_payers_lifetime_spending() {
+    build_and_execute(|| {
+        let pricing = VotingInterestPricing {
+            tiers: BoundedVec::truncate_from(vec![
+                PricingTier {
+                    threshold: 1_000,
+                    rate: Perbill::from_percent(50),
+                },
+                PricingTier {
+                    threshold: 2_000,
+                    rate: Perbill::from_percent(25),
+                },
+            ]),
+            time_multipliers: BoundedVec::default(),
+        };
+        assert_ok!(NodeVoting::set_voting_interest_pricing(
+            RuntimeOrigin::root(),
+            pricing
+        ));
+
+        // 1000 at the base rate and 500 at half of it
+        buy_votes(ALICE, 1_500);
+        assert_eq!(
+            UsersVotingInterests::<Test>::get(ALICE).unwrap().total,
+            1_250
+        );
+        // 500 at half the rate and 500 at a quarter of it
+        assert_eq!(NodeVoting::preview_voting_interest(ALICE, 1_000), 375);
+        buy_votes(ALICE, 1_000);
+        assert_eq!(
+            UsersVotingInterests::<Test>::get(ALICE).unwrap().total,
+            1_625
+        );
+
+        // another payer starts at the base rate
+        buy_votes(BOB, 500);
+        assert_eq!(UsersVotingInterests::<Test>::get(BOB).unwrap().total, 500);
+    });
+}
+
+#[test]
+fn voting_interest_gets_the_running_time_bonus() {
+    build_and_execute(|| {
+        let pricing = VotingInterestPricing {
+            tiers: BoundedVec::default(),
+            time_multipliers: BoundedVec::truncate_from(vec![
+                TimeMultiplier {
+                    until_session: 2,
+                    bonus: Percent::from_percent(20),
+                },
+                TimeMultiplier {
+                    until_session: 4,
+                    bonus: Percent::from_percent(10),
+                },
+            ]),
+        };
+        assert_ok!(NodeVoting::set_voting_interest_pricing(
+            RuntimeOrigin::root(),
+            pricing
+        ));
+
+        assert_eq!(NodeVoting::preview_voting_interest(ALICE, 100), 120);
+        NodeVoting::start_session(2);
+        assert_eq!(NodeVoting::preview_voting_interest(ALICE, 100), 110);
+        NodeVoting::start_session(4);
+        assert_eq!(NodeVoting::preview_voting_interest(ALICE, 100), 100);
+    });
+}
+
+#[test]
+fn pricing_tiers_must_ascend() {
+    build_and_execute(|| {
+        let tier = |threshold| PricingTier {
+            threshold,
+            rate: Perbill::from_percent(50),
+        };
+        let pricing = VotingInterestPricing {
+            tiers: BoundedVec::truncate_from(vec![tier(2_000), tier(1_000)]),
+            time_multipliers: BoundedVec::default(),
+        };
+        assert_noop!(
+            NodeVoting::set_voting_interest_pricing(RuntimeOrigin::root(), pricing.clone()),
+            Error::<Test>::PricingTiersNotAscending
+        );
+        assert_noop!(
+            NodeVoting::set_voting_interest_pricing(RuntimeOrigin::signed(ALICE), pricing),
+            BadOrigin
+        );
+    });
+}
+
+#[test]
+fn locked_delegation_counts_its_conviction() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+        assert_eq!(node_votes(ALICE), 100);
+        // a plain delegation weighs its raw votes and keeps no state
+        assert!(!DelegationWeights::<Test>::contains_key((ALICE, BOB)));
+
+        assert_noop!(
+            NodeVoting::lock_delegation(RuntimeOrigin::signed(BOB), ALICE, Conviction::None),
+            Error::<Test>::InvalidConviction
+        );
+        assert_ok!(NodeVoting::lock_delegation(
+            RuntimeOrigin::signed(BOB),
+            ALICE,
+            Conviction::Locked3x
+        ));
+        assert_eq!(node_votes(ALICE), 300);
+        assert_eq!(NodeVoting::delegation_weight(&BOB, &ALICE), 300);
+        // the reward manager settles the delegation at its weight before the lock
+        assert_eq!(settlements().last(), Some(&(BOB, ALICE, 100)));
+
+        assert_noop!(
+            NodeVoting::lock_delegation(RuntimeOrigin::signed(BOB), ALICE, Conviction::Locked1x),
+            Error::<Test>::CannotShortenDelegationLock
+        );
+        assert_noop!(
+            NodeVoting::try_remove_votes_from_candidate(RuntimeOrigin::signed(BOB), ALICE, 10),
+            Error::<Test>::DelegationLocked
+        );
+    });
+}
+
+#[test]
+fn expired_lock_falls_back_to_the_raw_votes() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+        assert_ok!(NodeVoting::lock_delegation(
+            RuntimeOrigin::signed(BOB),
+            ALICE,
+            Conviction::Locked2x
+        ));
+        assert_eq!(node_votes(ALICE), 200);
+
+        // two lock periods of two sessions each
+        start_session(3);
+        assert_eq!(node_votes(ALICE), 200);
+        start_session(4);
+        assert_eq!(node_votes(ALICE), 100);
+        assert!(!DelegationWeights::<Test>::contains_key((ALICE, BOB)));
+        assert_ok!(NodeVoting::try_remove_votes_from_candidate(
+            RuntimeOrigin::signed(BOB),
+            ALICE,
+            100
+        ));
+        assert_eq!(node_votes(ALICE), 0);
+    });
+}
+
+#[test]
+fn idle_delegations_decay_down_to_the_floor() {
+    build_and_execute(|| {
+        assert_ok!(NodeVoting::set_vote_decay(
+            RuntimeOrigin::root(),
+            VoteDecayParameters {
+                decay_per_session: Perbill::from_percent(10),
+                floor: Perbill::from_percent(50),
+            }
+        ));
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+
+        start_session(2);
+        assert_eq!(node_votes(ALICE), 81);
+        assert_eq!(settlements().last(), Some(&(BOB, ALICE, 100)));
+        // the sweep runs once per session
+        NodeVoting::on_idle(2, Weight::MAX);
+        assert_eq!(node_votes(ALICE), 81);
+
+        start_session(20);
+        assert_eq!(node_votes(ALICE), 50);
+
+        assert_ok!(NodeVoting::refresh_delegations(
+            RuntimeOrigin::signed(BOB),
+            1
+        ));
+        assert_eq!(node_votes(ALICE), 100);
+        assert_noop!(
+            NodeVoting::refresh_delegations(RuntimeOrigin::signed(BOB), 0),
+            Error::<Test>::TooManyDelegations
+        );
+    });
+}
+
+#[test]
+fn locked_delegations_do_not_decay() {
+    build_and_execute(|| {
+        assert_ok!(NodeVoting::set_vote_decay(
+            RuntimeOrigin::root(),
+            VoteDecayParameters {
+                decay_per_session: Perbill::from_percent(10),
+                floor: Perbill::zero(),
+            }
+        ));
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+        assert_ok!(NodeVoting::lock_delegation(
+            RuntimeOrigin::signed(BOB),
+            ALICE,
+            Conviction::Locked1x
+        ));
+
+        start_session(1);
+        assert_eq!(node_votes(ALICE), 100);
+        // decay starts once the lock has run out
+        start_session(3);
+        assert_eq!(node_votes(ALICE), 90);
+    });
+}
+
+#[test]
+fn candidates_reserve_a_deposit_for_their_metadata() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        assert_eq!(Balances::reserved_balance(ALICE), 10);
+        assert_eq!(NodeMetadataDeposits::<Test>::get(ALICE), 10);
+
+        // an account without funds cannot put metadata on chain
+        assert_noop!(
+            NodeVoting::submit_candidacy(RuntimeOrigin::signed(5), metadata(50)),
+            Error::<Test>::InsufficientBalanceForMetadataDeposit
+        );
+
+        assert_ok!(NodeVoting::remove_candidacy(RuntimeOrigin::signed(ALICE)));
+        NodeVoting::start_session(2);
+        assert_eq!(Balances::reserved_balance(ALICE), 0);
+        assert_eq!(Balances::free_balance(ALICE), 100_000);
+        assert!(!NodeMetadataDeposits::<Test>::contains_key(ALICE));
+    });
+}
+
+#[test]
+fn controller_manages_the_candidacy_of_its_stash() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        assert_ok!(NodeVoting::set_controller(
+            RuntimeOrigin::signed(ALICE),
+            BOB
+        ));
+        assert_eq!(NodeVoting::stash_of(&BOB), ALICE);
+        assert_eq!(StashOf::<Test>::convert(BOB), Some(ALICE));
+        assert_eq!(StashOf::<Test>::convert(CHARLIE), Some(CHARLIE));
+        // the default converter keeps mapping accounts to themselves
+        assert_eq!(ConvertAccountId::<Test>::convert(BOB), Some(BOB));
+
+        // the controller acts for the stash, the stash itself no longer can
+        assert_ok!(NodeVoting::change_candidate_supporter_share(
+            RuntimeOrigin::signed(BOB),
+            45
+        ));
+        assert!(PendingSharePercentChanges::<Test>::contains_key(ALICE));
+        assert_noop!(
+            NodeVoting::cancel_supporter_share_change(RuntimeOrigin::signed(ALICE)),
+            Error::<Test>::NotController
+        );
+        assert_noop!(
+            NodeVoting::submit_candidacy(RuntimeOrigin::signed(BOB), metadata(50)),
+            Error::<Test>::AccountIsController
+        );
+
+        submit_candidacy(CHARLIE);
+        assert_noop!(
+            NodeVoting::set_controller(RuntimeOrigin::signed(CHARLIE), BOB),
+            Error::<Test>::ControllerAlreadyPaired
+        );
+
+        // passing the stash itself removes the controller
+        assert_ok!(NodeVoting::set_controller(
+            RuntimeOrigin::signed(ALICE),
+            ALICE
+        ));
+        assert_eq!(NodeVoting::stash_of(&BOB), BOB);
+        assert_ok!(NodeVoting::cancel_supporter_share_change(
+            RuntimeOrigin::signed(ALICE)
+        ));
+    });
+}
+
+#[test]
+fn vote_manager_moves_votes_within_its_session_allowance() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 300);
+        assert_ok!(NodeVoting::set_vote_manager(
+            RuntimeOrigin::signed(BOB),
+            CHARLIE,
+            150,
+            Some(3)
+        ));
+        let delegate_for_bob = |manager: u64, votes: u64| {
+            NodeVoting::manage_votes_of(
+                RuntimeOrigin::signed(manager),
+                BOB,
+                ManagedVoteCall::DelegateVotes(vec![ValidatorDelegations {
+                    candidate: ALICE,
+                    votes,
+                }]),
+            )
+        };
+
+        assert_ok!(delegate_for_bob(CHARLIE, 100));
+        assert_eq!(node_votes(ALICE), 100);
+        assert_noop!(
+            delegate_for_bob(CHARLIE, 60),
+            Error::<Test>::ManagedVotesExceedLimit
+        );
+        assert_noop!(delegate_for_bob(DAVE, 10), Error::<Test>::NotVoteManager);
+
+        // the allowance starts over every session
+        NodeVoting::start_session(1);
+        assert_ok!(delegate_for_bob(CHARLIE, 60));
+        assert_eq!(ManagedVotesMoved::<Test>::get(BOB), (1, 60));
+
+        NodeVoting::start_session(3);
+        assert_noop!(
+            delegate_for_bob(CHARLIE, 10),
+            Error::<Test>::VoteManagerExpired
+        );
+
+        assert_ok!(NodeVoting::revoke_vote_manager(RuntimeOrigin::signed(BOB)));
+        assert_noop!(
+            NodeVoting::revoke_vote_manager(RuntimeOrigin::signed(BOB)),
+            Error::<Test>::NoVoteManager
+        );
+    });
+}
+
+#[test]
+fn batch_redistribution_applies_moves_in_order() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        submit_candidacy(CHARLIE);
+        submit_candidacy(DAVE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+
+        assert_ok!(NodeVoting::redistribute_votes_batch(
+            RuntimeOrigin::signed(BOB),
+            vec![vote_move(ALICE, CHARLIE, 60), vote_move(CHARLIE, DAVE, 20)]
+        ));
+        assert_eq!(node_votes(ALICE), 40);
+        assert_eq!(node_votes(CHARLIE), 40);
+        assert_eq!(node_votes(DAVE), 20);
+        assert_eq!(
+            UsersVotingInterests::<Test>::get(BOB).unwrap().delegated,
+            100
+        );
+        System::assert_last_event(RuntimeEvent::NodeVoting(Event::VotesRedistributed(
+            BOB, 2, 80,
+        )));
+    });
+}
+
+#[test]
+fn failing_batch_redistribution_changes_nothing() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        submit_candidacy(CHARLIE);
+        submit_candidacy(DAVE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+
+        assert_noop!(
+            NodeVoting::redistribute_votes_batch(
+                RuntimeOrigin::signed(BOB),
+                vec![vote_move(ALICE, CHARLIE, 60), vote_move(ALICE, DAVE, 50)]
+            ),
+            Error::<Test>::AttemptingToRemoveMoreVotesThanDelegated
+        );
+        assert_noop!(
+            NodeVoting::redistribute_votes_batch(
+                RuntimeOrigin::signed(BOB),
+                vec![vote_move(ALICE, CHARLIE, 10), vote_move(DAVE, CHARLIE, 10)]
+            ),
+            Error::<Test>::VoterDidntDelegateToThisCandidate
+        );
+        assert_noop!(
+            NodeVoting::redistribute_votes_batch(
+                RuntimeOrigin::signed(BOB),
+                vec![vote_move(ALICE, ALICE, 10)]
+            ),
+            Error::<Test>::InvalidVoteMove
+        );
+        assert_noop!(
+            NodeVoting::redistribute_votes_batch(RuntimeOrigin::signed(BOB), vec![]),
+            Error::<Test>::EmptyVoteMoveList
+        );
+    });
+}
+
+#[test]
+fn batch_redistribution_respects_locks() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        submit_candidacy(CHARLIE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 50);
+        delegate(BOB, CHARLIE, 50);
+        assert_ok!(NodeVoting::lock_delegation(
+            RuntimeOrigin::signed(BOB),
+            CHARLIE,
+            Conviction::Locked1x
+        ));
+
+        assert_noop!(
+            NodeVoting::redistribute_votes_batch(
+                RuntimeOrigin::signed(BOB),
+                vec![vote_move(ALICE, CHARLIE, 10)]
+            ),
+            Error::<Test>::CannotAddToLockedDelegation
+        );
+        assert_noop!(
+            NodeVoting::redistribute_votes_batch(
+                RuntimeOrigin::signed(BOB),
+                vec![vote_move(CHARLIE, ALICE, 10)]
+            ),
+            Error::<Test>::DelegationLocked
+        );
+    });
+}
+
+#[test]
+fn validator_set_size_follows_the_governance_bounds() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        submit_candidacy(CHARLIE);
+        buy_votes(BOB, 500);
+        delegate(BOB, ALICE, 300);
+        delegate(BOB, CHARLIE, 200);
+
+        for (min, max) in [(0, 2), (3, 2), (1, 6)] {
+            assert_noop!(
+                NodeVoting::set_validator_set_bounds(RuntimeOrigin::root(), min, max),
+                Error::<Test>::InvalidValidatorSetBounds
+            );
+        }
+        assert_noop!(
+            NodeVoting::set_validator_set_bounds(RuntimeOrigin::signed(ALICE), 1, 2),
+            BadOrigin
+        );
+        assert_ok!(NodeVoting::set_validator_set_bounds(
+            RuntimeOrigin::root(),
+            1,
+            2
+        ));
+        assert_eq!(NodeVoting::new_session(1), Some(vec![GENESIS_NODE, ALICE]));
+    });
+}
+
+#[test]
+fn validator_set_is_frozen_below_the_minimum() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        assert_ok!(NodeVoting::set_validator_set_bounds(
+            RuntimeOrigin::root(),
+            3,
+            5
+        ));
+        assert_eq!(NodeVoting::new_session(1), None);
+        assert!(EmergencyMode::<Test>::get());
+        System::assert_last_event(RuntimeEvent::NodeVoting(Event::ValidatorSetFrozen(1, 2)));
+
+        submit_candidacy(CHARLIE);
+        assert_eq!(NodeVoting::new_session(2).map(|set| set.len()), Some(3));
+        assert!(!EmergencyMode::<Test>::get());
+        System::assert_last_event(RuntimeEvent::NodeVoting(Event::EmergencyModeExited(2)));
+    });
+}
+
+#[test]
+fn invulnerable_nodes_are_always_elected() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        submit_candidacy(DAVE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+        assert_ok!(NodeVoting::set_validator_set_bounds(
+            RuntimeOrigin::root(),
+            1,
+            2
+        ));
+        assert_noop!(
+            NodeVoting::set_invulnerable_nodes(RuntimeOrigin::root(), vec![ALICE, CHARLIE, DAVE]),
+            Error::<Test>::TooManyInvulnerableNodes
+        );
+        assert_ok!(NodeVoting::set_invulnerable_nodes(
+            RuntimeOrigin::root(),
+            vec![DAVE, DAVE]
+        ));
+        assert_eq!(InvulnerableNodes::<Test>::get().into_inner(), vec![DAVE]);
+
+        // dave has no votes but takes a seat ahead of the vote ranking
+        assert_eq!(NodeVoting::new_session(1), Some(vec![DAVE, GENESIS_NODE]));
+
+        assert_ok!(NodeVoting::set_invulnerable_nodes(
+            RuntimeOrigin::root(),
+            vec![ALICE, DAVE]
+        ));
+        assert_noop!(
+            NodeVoting::set_validator_set_bounds(RuntimeOrigin::root(), 1, 1),
+            Error::<Test>::TooManyInvulnerableNodes
+        );
+    });
+}
+
+#[test]
+fn chilled_candidates_keep_their_votes_but_are_not_elected() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+
+        assert_ok!(NodeVoting::chill(RuntimeOrigin::signed(ALICE)));
+        assert_eq!(
+            CandidateStatuses::<Test>::get(ALICE),
+            CandidateStatus::Chilled
+        );
+        assert!(!NodeVoting::is_electable(&ALICE));
+        assert_eq!(node_votes(ALICE), 100);
+        assert_eq!(NodeVoting::new_session(1), Some(vec![GENESIS_NODE]));
+        assert_noop!(
+            NodeVoting::chill(RuntimeOrigin::signed(ALICE)),
+            Error::<Test>::CandidateNotActive
+        );
+
+        assert_ok!(NodeVoting::unchill(RuntimeOrigin::signed(ALICE)));
+        assert!(NodeVoting::is_electable(&ALICE));
+        assert_noop!(
+            NodeVoting::unchill(RuntimeOrigin::signed(ALICE)),
+            Error::<Test>::CandidateNotChilled
+        );
+    });
+}
+
+#[test]
+fn retiring_candidates_are_removed_after_the_notice_period() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 100);
+
+        assert_ok!(NodeVoting::remove_candidacy(RuntimeOrigin::signed(ALICE)));
+        assert_eq!(
+            CandidateStatuses::<Test>::get(ALICE),
+            CandidateStatus::Retiring { effective: 2 }
+        );
+        assert_noop!(
+            NodeVoting::remove_candidacy(RuntimeOrigin::signed(ALICE)),
+            Error::<Test>::CandidateDoesNotExist
+        );
+
+        // an offence during the notice period is still recorded
+        report_offence(ALICE);
+        assert_eq!(LastJailedSession::<Test>::get(ALICE), Some(0));
+        assert_eq!(
+            CandidateStatuses::<Test>::get(ALICE),
+            CandidateStatus::Retiring { effective: 2 }
+        );
+
+        NodeVoting::start_session(1);
+        assert_eq!(node_votes(ALICE), 100);
+        NodeVoting::start_session(2);
+        assert!(!NodeAccumulativeVotes::<Test>::contains_key(ALICE));
+        assert_eq!(UsersVotingInterests::<Test>::get(BOB).unwrap().delegated, 0);
+        System::assert_has_event(RuntimeEvent::NodeVoting(Event::CandidacyRemoved(ALICE)));
+    });
+}
+
+#[test]
+fn offences_jail_candidates_for_the_jail_duration() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        assert_ok!(NodeVoting::chill(RuntimeOrigin::signed(ALICE)));
+        report_offence(ALICE);
+        assert_eq!(
+            CandidateStatuses::<Test>::get(ALICE),
+            CandidateStatus::Jailed {
+                until: 3,
+                chilled: true
+            }
+        );
+        assert_noop!(
+            NodeVoting::unchill(RuntimeOrigin::signed(ALICE)),
+            Error::<Test>::CandidateNotChilled
+        );
+
+        // a chilled candidate goes back to being chilled once released
+        NodeVoting::start_session(3);
+        assert_eq!(
+            CandidateStatuses::<Test>::get(ALICE),
+            CandidateStatus::Chilled
+        );
+    });
+}
+
+#[test]
+fn jailing_is_recorded_for_every_session_it_covers() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        assert_noop!(
+            NodeVoting::jail_candidate(RuntimeOrigin::signed(BOB), ALICE, 2),
+            BadOrigin
+        );
+        assert_ok!(NodeVoting::jail_candidate(RuntimeOrigin::root(), ALICE, 2));
+        assert!(!NodeVoting::is_electable(&ALICE));
+        assert!(<NodeVoting as NodeVotingProvider<u64>>::was_jailed_in(
+            &ALICE, 0
+        ));
+
+        NodeVoting::start_session(1);
+        assert_eq!(LastJailedSession::<Test>::get(ALICE), Some(1));
+
+        NodeVoting::start_session(2);
+        assert!(NodeVoting::is_electable(&ALICE));
+        assert!(<NodeVoting as NodeVotingProvider<u64>>::was_jailed_in(
+            &ALICE, 1
+        ));
+        assert!(!<NodeVoting as NodeVotingProvider<u64>>::was_jailed_in(
+            &ALICE, 2
+        ));
+    });
+}
+
+#[test]
+fn admin_can_jail_candidates() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        PalletAdmin::<Test>::put(DAVE);
+        assert_ok!(NodeVoting::jail_candidate(
+            RuntimeOrigin::signed(DAVE),
+            ALICE,
+            1
+        ));
+        System::assert_last_event(RuntimeEvent::NodeVoting(Event::CandidateJailed(ALICE, 1)));
+    });
+}
+
+#[test]
+fn genesis_candidates_start_with_bootstrap_votes() {
+    build_and_execute(|| {
+        assert_eq!(node_votes(GENESIS_NODE), BOOTSTRAP_VOTES);
+        assert_eq!(BootstrapVotes::<Test>::get(GENESIS_NODE), BOOTSTRAP_VOTES);
+        assert_eq!(CurrentNumberOfCandidatesNodes::<Test>::get(), 1);
+        assert_eq!(
+            <NodeVoting as NodeVotingProvider<u64>>::delegated_votes(&GENESIS_NODE),
+            0
+        );
+    });
+}
+
+#[test]
+fn bootstrap_votes_cannot_be_moved() {
+    build_and_execute(|| {
+        buy_votes(BOB, 100);
+        delegate(BOB, GENESIS_NODE, 100);
+        assert_eq!(node_votes(GENESIS_NODE), BOOTSTRAP_VOTES + 100);
+        assert_noop!(
+            NodeVoting::try_remove_votes_from_candidate(
+                RuntimeOrigin::signed(BOB),
+                GENESIS_NODE,
+                101
+            ),
+            Error::<Test>::AttemptingToRemoveMoreVotesThanDelegated
+        );
+        assert_ok!(NodeVoting::try_remove_votes_from_candidate(
+            RuntimeOrigin::signed(BOB),
+            GENESIS_NODE,
+            100
+        ));
+        assert_eq!(node_votes(GENESIS_NODE), BOOTSTRAP_VOTES);
+    });
+}
+
+#[test]
+fn repair_rebuilds_the_derived_vote_totals() {
+    build_and_execute(|| {
+        submit_candidacy(ALICE);
+        buy_votes(BOB, 100);
+        delegate(BOB, ALICE, 60);
+        delegate(BOB, GENESIS_NODE, 40);
+
+        NodeAccumulativeVotes::<Test>::insert(ALICE, 5);
+        NodeToUserVotesTotals::<Test>::remove((GENESIS_NODE, BOB));
+        UsersVotingInterests::<Test>::mutate(BOB, |interest| {
+            interest.as_mut().unwrap().delegated = 0
+        });
+        CurrentNumberOfCandidatesNodes::<Test>::put(7);
+        assert!(NodeVoting::do_try_state().is_err());
+
+        assert_noop!(
+            NodeVoting::repair_vote_totals(RuntimeOrigin::signed(ALICE)),
+            BadOrigin
+        );
+        assert_ok!(NodeVoting::repair_vote_totals(RuntimeOrigin::root()));
+        assert_eq!(node_votes(ALICE), 60);
+        assert_eq!(node_votes(GENESIS_NODE), BOOTSTRAP_VOTES + 40);
+        assert_eq!(
+            UsersVotingInterests::<Test>::get(BOB).unwrap().delegated,
+            100
+        );
+        assert_eq!(CurrentNumberOfCandidatesNodes::<Test>::get(), 2);
+        System::assert_last_event(RuntimeEvent::NodeVoting(Event::VoteTotalsRepaired(2, 2)));
+    });
+}
+
+#[test]
+fn reward_contract_is_not_registered_here() {
+    build_and_execute(|| {
+        let entry = ContractEntry {
+            address: DAVE,
+            selector: ContractOperation::UpdateRewards.legacy_selector(),
+            abi_version: 1,
+        };
+        assert_noop!(
+            NodeVoting::register_contract(
+                RuntimeOrigin::root(),
+                ContractOperation::UpdateRewards,
+                Some(entry),
+                vec![]
+            ),
+            Error::<Test>::ContractManagedElsewhere
+        );
+    });
+}
+
+#[test]
+fn contracts_are_registered_after_a_dry_run() {
+    build_and_execute(|| {
+        let entry = ContractEntry {
+            address: DAVE,
+            selector: ContractOperation::BurnForVotes.legacy_selector(),
+            abi_version: 1,
+        };
+        assert_noop!(
+            NodeVoting::register_contract(
+                RuntimeOrigin::signed(ALICE),
+                ContractOperation::BurnForVotes,
+                Some(entry.clone()),
+                vec![]
+            ),
+            BadOrigin
+        );
+        // there is no contract at the address, so it cannot respond
+        assert_noop!(
+            NodeVoting::register_contract(
+                RuntimeOrigin::root(),
+                ContractOperation::BurnForVotes,
+                Some(entry.clone()),
+                vec![]
+            ),
+            Error::<Test>::ContractDryRunFailed
+        );
+        assert_ok!(NodeVoting::dry_run_contract(
+            RuntimeOrigin::root(),
+            ContractOperation::BurnForVotes,
+            entry,
+            vec![]
+        ));
+        System::assert_last_event(RuntimeEvent::NodeVoting(Event::ContractDryRun(
+            ContractOperation::BurnForVotes,
+            DAVE,
+            false,
+        )));
+        // the burn contract hook is passed to the registered main pool
+        assert_noop!(
+            NodeVoting::set_burn_contract_hook(RuntimeOrigin::root(), Some(CHARLIE)),
+            Error::<Test>::ContractNotRegistered
+        );
+
+        assert_ok!(NodeVoting::register_contract(
+            RuntimeOrigin::root(),
+            ContractOperation::BurnForVotes,
+            None,
+            vec![]
+        ));
+        System::assert_last_event(RuntimeEvent::NodeVoting(Event::ContractDeregistered(
+            ContractOperation::BurnForVotes,
+        )));
+    });
+}